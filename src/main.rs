@@ -1,6 +1,7 @@
 //! A simple 3D scene with light shining over a cube sitting on a plane.
 
 use bevy::prelude::*;
+use std::sync::Arc;
 use physics::shape::ShapeConstructor;
 
 mod physics;
@@ -9,6 +10,7 @@ mod visuals;
 // mod physics::bodies::;
 use crate::physics::{
     body,
+    material,
     scene,
     shape,
     vec::{
@@ -24,7 +26,7 @@ use crate::physics::{
     quat::quat
 };
 use body::Body as myBody;
-use body::ColorT;
+use material::PhysicsMaterial;
 
 use shape::ShapeT;
 
@@ -33,12 +35,19 @@ use scene::Scene as myScene;
 use vec3d::Vec3d as myVec3;
 use quat::Quat as myQuat;
 
-use crate::visuals::adapters;
+use crate::visuals::{
+    adapters,
+    plugin
+};
 use adapters::BodyAdapter;
+use plugin::{
+    PhysicsPlugin,
+    PhysicsScene
+};
 
 fn main() {
     App::new()
-        .add_plugins(DefaultPlugins)
+        .add_plugins((DefaultPlugins, PhysicsPlugin))
         .add_systems(Startup, setup)
         .run();
 }
@@ -48,50 +57,53 @@ fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    mut scene: ResMut<PhysicsScene>,
 ) {
+    // The ground is a grid of triangles, bodies roll across its seams like on level geometry
+    let cells: usize = 10;
+    let cell_size: f64 = 1.0;
+    let half_size: f64 = cells as f64 * cell_size * 0.5;
+    let mut vertices: Vec<myVec3> = Vec::new();
+    let mut indices: Vec<usize> = Vec::new();
+    for i in 0..=cells {
+        for j in 0..=cells {
+            vertices.push(myVec3::new(i as f64 * cell_size - half_size, 0.0, j as f64 * cell_size - half_size));
+        }
+    }
+    for i in 0..cells {
+        for j in 0..cells {
+            // Two triangles per cell, facing up
+            let corner: usize = i * (cells + 1) + j;
+            let next_row: usize = corner + cells + 1;
+            indices.extend_from_slice(&[corner, corner + 1, next_row, next_row, corner + 1, next_row + 1]);
+        }
+    }
+
+    // Triangle meshes are always static, the scene gives the ground an infinite mass
     let ground = myBody::new(
-        ShapeT::new_box(10.0, 0.01, 10.0),
+        ShapeT::new_triangle_mesh(&vertices, &indices),
         myQuat::zero(),
         myVec3::new(0.0, -2.0, 0.0),
-        1000.0,
         myVec3::zero(),
-        ColorT::WHITE
+        Arc::new(PhysicsMaterial::default())
     );
-    let ground_bundle = BodyAdapter::new(ground);
+    let ground_bundle = BodyAdapter::new(ground.clone());
+    let ground_handle = scene.0.add_body(ground);
 
     let my_sphere = myBody::new(
         ShapeT::new_sphere(1.0),
         myQuat::zero(),
         myVec3::zero(),
-        1.0,
         myVec3::zero(),
-        ColorT::RED
+        Arc::new(PhysicsMaterial::rubber())
     );
-    let sphere_bundle = BodyAdapter::new(my_sphere);
+    let sphere_bundle = BodyAdapter::new(my_sphere.clone());
+    let sphere_handle = scene.0.add_body(my_sphere);
 
-    
-    // box base
-    commands.spawn(PbrBundle {
-        mesh: meshes.add(
-            ground_bundle.clone().get_mesh().into()
-        ),
-        material: materials.add(
-            ground_bundle.clone().get_material().into()
-        ),
-        transform: ground_bundle.clone().get_transform(),
-        ..default()
-    });
+    // ground
+    ground_bundle.spawn(&mut commands, &mut meshes, &mut materials, ground_handle);
     // sphere
-    commands.spawn(PbrBundle {
-        mesh: meshes.add(
-            sphere_bundle.clone().get_mesh().into()
-        ),
-        material: materials.add(
-            sphere_bundle.clone().get_material().into()
-        ),
-        transform: sphere_bundle.clone().get_transform(),
-        ..default()
-    });
+    sphere_bundle.spawn(&mut commands, &mut meshes, &mut materials, sphere_handle);
     
     // light
     commands.spawn(PointLightBundle {
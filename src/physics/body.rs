@@ -1,39 +1,62 @@
 use crate::physics::shape::Shape;
 use crate::physics::quat::quat::Quat;
 use crate::physics::vec::vec3d::Vec3d;
+use crate::physics::mat::mat3::Mat3;
 use crate::physics::shape::*;
+use crate::physics::material::{
+    ColorT,
+    PhysicsMaterial
+};
+use std::sync::Arc;
 
-#[derive(Debug, Clone, Copy)]
-pub enum ColorT {
-    WHITE,
-    RED,
-    BLUE,
-    GREEN,
-    YELLOW
+/// How a body moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyType {
+    Dynamic, // Moved by gravity, contacts and joints
+    Static, // Never moves, like a wall or the ground
+    Kinematic // Moved by its velocity or towards its target, pushes dynamic bodies but is never pushed back
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Body {
     pub shape: ShapeT,
     pub orientation: Quat,
     pub position: Vec3d,
-    pub inv_mass: f64, // In order to represent infinite mass, like a wall or earth
+    pub body_type: BodyType,
+    pub inv_mass: f64, // Used while the body is dynamic, get_inv_mass gives the one the body reacts with
     pub linear_velocity: Vec3d,
-    pub color: ColorT
+    pub angular_velocity: Vec3d,
+    pub target: Option<(Vec3d, Quat)>, // Pose a kinematic body reaches at the end of the next step, its velocity is ignored while set
+    pub material: Arc<PhysicsMaterial>, // Shared by every body made of the same thing
+    pub continuous: bool // Opt in to continuous collision detection, for fast bodies that could tunnel
 }
 
 impl Body {
     pub const GRAVITY: f64 = 9.8; // m/s^2
+    pub const MAX_ANGULAR_SPEED: f64 = 30.0; // rad/s, keeps the integration stable
 
-    pub fn new(shape: ShapeT, orientation: Quat, position: Vec3d, mass: f64, velocity: Vec3d, color: ColorT) -> Self {
-        Body {
+    /// The mass comes from the volume of the shape times the density of the material,
+    /// shapes without a volume make a static body, set_mass overrides it
+    pub fn new(shape: ShapeT, orientation: Quat, position: Vec3d, velocity: Vec3d, material: Arc<PhysicsMaterial>) -> Self {
+        let mut body: Body = Body {
             shape,
             orientation,
             position,
-            inv_mass: 1.0 / mass,
+            body_type: BodyType::Dynamic,
+            inv_mass: 0.0,
             linear_velocity: velocity,
-            color,
+            angular_velocity: Vec3d::zero(),
+            target: None,
+            material,
+            continuous: false
+        };
+        let volume: f64 = body.shape.get_volume();
+        if volume > 0.0 {
+            body.set_mass(volume * body.material.density);
+        } else {
+            body.body_type = BodyType::Static;
         }
+        body
     }
 
     pub fn new_shape(shape: ShapeT) -> Self {
@@ -41,9 +64,13 @@ impl Body {
             shape: shape,
             orientation: Quat::zero(),
             position: Vec3d::zero(),
+            body_type: BodyType::Dynamic,
             inv_mass: 1.0,
             linear_velocity: Vec3d::zero(),
-            color: ColorT::WHITE,
+            angular_velocity: Vec3d::zero(),
+            target: None,
+            material: Arc::new(PhysicsMaterial::default()),
+            continuous: false
         }
     }
 
@@ -52,12 +79,64 @@ impl Body {
             shape: ShapeT::new_sphere(1.0),
             orientation: Quat::zero(),
             position: Vec3d::zero(),
+            body_type: BodyType::Dynamic,
             inv_mass: 1.0,
             linear_velocity: Vec3d::zero(),
-            color: ColorT::WHITE,
+            angular_velocity: Vec3d::zero(),
+            target: None,
+            material: Arc::new(PhysicsMaterial::default()),
+            continuous: false
+        }
+    }
+
+    /// kg, infinite for bodies that can't be pushed
+    pub fn get_mass(&self) -> f64 {
+        let inv_mass: f64 = self.get_inv_mass();
+        if inv_mass == 0.0 {
+            return f64::INFINITY
+        }
+        1.0 / inv_mass
+    }
+
+    /// Explicit mass instead of the one from the density, zero, less or infinite gives an infinite mass.
+    /// Whether the body moves is up to body_type
+    pub fn set_mass(&mut self, mass: f64) {
+        self.inv_mass = if mass > 0.0 && mass.is_finite() { 1.0 / mass } else { 0.0 };
+    }
+
+    /// Static and kinematic bodies react to contacts and joints as if their mass was infinite
+    pub fn get_inv_mass(&self) -> f64 {
+        match self.body_type {
+            BodyType::Dynamic => self.inv_mass,
+            BodyType::Static | BodyType::Kinematic => 0.0
         }
     }
 
+    /// Sets the velocities that take a kinematic body to its target over the next dt_sec
+    pub fn move_towards_target(&mut self, dt_sec: f64) {
+        let Some((position, orientation)) = self.target else { return };
+        if self.body_type != BodyType::Kinematic || dt_sec <= 0.0 {
+            return;
+        }
+
+        // The body moves and turns around its center of mass
+        let center_mass: Vec3d = self.get_center_of_mass_model_space();
+        let target_cm: Vec3d = position + orientation.rotate_point(center_mass);
+        self.linear_velocity = (target_cm - self.get_center_of_mass_world_space()) * (1.0 / dt_sec);
+
+        // Turn the short way round
+        let mut dq: Quat = (orientation * self.orientation.inverse()).normalize();
+        if dq.w < 0.0 {
+            dq *= -1.0;
+        }
+        let axis: Vec3d = dq.get_xyz();
+        self.angular_velocity = if axis.get_magnitude_squared() > f64::EPSILON {
+            axis.normalize() * (dq.get_angle() / dt_sec)
+        } else {
+            Vec3d::zero()
+        };
+    }
+
     pub fn get_center_of_mass_world_space(&self) -> Vec3d {
         let center_mass: Vec3d = self.shape.get_center_mass();
         let pos: Vec3d = self.position + self.orientation.rotate_point(center_mass);
@@ -76,25 +155,141 @@ impl Body {
         return body_space
     }
 
-    pub fn body_space_to_world_space(&self, world: &Vec3d) -> Vec3d {
-        let center_mass_world_space: Vec3d = self.world_space_to_body_space(world);
-        let world_space: Vec3d = center_mass_world_space + self.orientation.rotate_point(*world);
+    pub fn body_space_to_world_space(&self, body: &Vec3d) -> Vec3d {
+        let center_mass_world_space: Vec3d = self.get_center_of_mass_world_space();
+        let world_space: Vec3d = center_mass_world_space + self.orientation.rotate_point(*body);
         return world_space
     }
 
+    pub fn get_inverse_inertia_tensor_body_space(&self) -> Mat3 {
+        // Bodies with infinite mass can't be turned, and their shape may have no inertia to invert
+        let inv_mass: f64 = self.get_inv_mass();
+        if inv_mass == 0.0 {
+            return Mat3::zero();
+        }
+
+        let inertia_tensor: Mat3 = self.shape.get_inertia_tensor();
+        inertia_tensor.inverse() * inv_mass
+    }
+
+    pub fn get_inverse_inertia_tensor_world_space(&self) -> Mat3 {
+        let inv_inertia: Mat3 = self.get_inverse_inertia_tensor_body_space();
+        self.tensor_body_space_to_world_space(inv_inertia)
+    }
+
+    fn tensor_body_space_to_world_space(&self, tensor: Mat3) -> Mat3 {
+        // I_world = R * I_body * R^T
+        // rotate_matrix rotates the rows, which gives I * R^T,
+        // rotating the rows of its transpose again gives R * I * R^T
+        let half_rotated: Mat3 = self.orientation.rotate_matrix(tensor);
+        self.orientation.rotate_matrix(half_rotated.transpose())
+    }
+
+    pub fn apply_impulse(&mut self, impulse_point: Vec3d, impulse: Vec3d) {
+        if self.get_inv_mass() == 0.0 {
+            return;
+        }
+
+        // impulse_point is in world space, at the location the impulse is applied
+        self.apply_impulse_linear(impulse);
+
+        // L = r x p
+        let position: Vec3d = self.get_center_of_mass_world_space();
+        let r: Vec3d = impulse_point - position;
+        let d_l: Vec3d = r.cross(impulse);
+        self.apply_impulse_angular(d_l);
+    }
+
     pub fn apply_impulse_linear(&mut self, impulse: Vec3d) {
-        if self.inv_mass == 0.0 {
+        let inv_mass: f64 = self.get_inv_mass();
+        if inv_mass == 0.0 {
             return;
         }
         // dv = J / m = dp / m
-        self.linear_velocity = impulse * self.inv_mass;
+        self.linear_velocity += impulse * inv_mass;
+    }
+
+    pub fn apply_impulse_angular(&mut self, impulse: Vec3d) {
+        if self.get_inv_mass() == 0.0 {
+            return;
+        }
+
+        // L = I w = r x p
+        // dL = I dw = r x J
+        // => dw = I^-1 * (r x J)
+        self.angular_velocity += self.get_inverse_inertia_tensor_world_space() * impulse;
+
+        if self.angular_velocity.get_magnitude() > Body::MAX_ANGULAR_SPEED {
+            self.angular_velocity = self.angular_velocity.normalize() * Body::MAX_ANGULAR_SPEED;
+        }
+    }
+
+    /// Furthest point of the body along dir in world space, pushed out by bias
+    pub fn support(&self, dir: &Vec3d, bias: f64) -> Vec3d {
+        let dir_body_space: Vec3d = self.orientation.inverse().rotate_point(*dir);
+        let point: Vec3d = self.shape.support(&dir_body_space);
+        self.position + self.orientation.rotate_point(point) + dir.normalize() * bias
+    }
+
+    pub fn fastest_linear_speed(&self, dir: &Vec3d) -> f64 {
+        // The shape works in its own space
+        let inverse_orient: Quat = self.orientation.inverse();
+        let angular_velocity: Vec3d = inverse_orient.rotate_point(self.angular_velocity);
+        let dir_body_space: Vec3d = inverse_orient.rotate_point(*dir);
+        self.shape.fastest_linear_speed(&angular_velocity, &dir_body_space)
+    }
+
+    pub fn update(&mut self, dt_sec: f64) {
+        // Position update
+        // dx = v * dt
+        self.position += self.linear_velocity * dt_sec;
+
+        // The body rotates around its center of mass, not around its position
+        let position_cm: Vec3d = self.get_center_of_mass_world_space();
+        let cm_to_pos: Vec3d = self.position - position_cm;
+
+        // Euler's equation: T_external = I * a + w x (I * w)
+        // T_external = 0 because impulses were already applied to the angular velocity
+        // a = I^-1 * ((I * w) x w), the precession of a spinning body
+        // Kinematic bodies turn exactly as they are told
+        if self.body_type == BodyType::Dynamic {
+            let inertia_tensor: Mat3 = self.tensor_body_space_to_world_space(self.shape.get_inertia_tensor());
+            let alpha: Vec3d = inertia_tensor.inverse() *
+                (inertia_tensor * self.angular_velocity).cross(self.angular_velocity);
+            self.angular_velocity += alpha * dt_sec;
+        }
+
+        // Update orientation
+        // dq = (w * dt) as an axis angle rotation
+        let d_angle: Vec3d = self.angular_velocity * dt_sec;
+        let dq: Quat = Quat::from_axis_angle(d_angle, d_angle.get_magnitude());
+        self.orientation = (dq * self.orientation).normalize();
+
+        // Get the new model position
+        self.position = position_cm + dq.rotate_point(cm_to_pos);
+    }
+
+    /// Stand-in body for one child of a compound shape, placed where the child is in world space
+    pub fn child_body(&self, child: &ChildShape) -> Body {
+        Body {
+            shape: child.shape.clone(),
+            orientation: (self.orientation * child.orientation).normalize(),
+            position: self.position + self.orientation.rotate_point(child.position),
+            body_type: self.body_type,
+            inv_mass: self.inv_mass,
+            linear_velocity: self.linear_velocity,
+            angular_velocity: self.angular_velocity,
+            target: None,
+            material: self.material.clone(),
+            continuous: self.continuous
+        }
     }
 
-    pub fn get_shape(self) -> ShapeT {
-        self.shape
+    pub fn get_shape(&self) -> &ShapeT {
+        &self.shape
     }
 
-    pub fn get_color(self) -> ColorT {
-        self.color
+    pub fn get_color(&self) -> ColorT {
+        self.material.color
     }
 }
\ No newline at end of file
@@ -0,0 +1,127 @@
+use crate::physics::vec::vec3d::Vec3d;
+
+/// Axis aligned bounding box
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub mins: Vec3d,
+    pub maxs: Vec3d
+}
+
+impl Aabb {
+    pub fn new(mins: Vec3d, maxs: Vec3d) -> Self {
+        Aabb {mins, maxs}
+    }
+
+    /// Bounds that contain nothing, expanding them by a point makes them contain just that point
+    pub fn empty() -> Self {
+        Aabb {
+            mins: Vec3d::new(f64::MAX, f64::MAX, f64::MAX),
+            maxs: Vec3d::new(f64::MIN, f64::MIN, f64::MIN)
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Aabb::empty();
+    }
+
+    pub fn does_intersect(&self, other: &Aabb) -> bool {
+        (0..3).all(|i: usize| self.maxs[i] >= other.mins[i] && self.mins[i] <= other.maxs[i])
+    }
+
+    pub fn contains_point(&self, point: &Vec3d) -> bool {
+        (0..3).all(|i: usize| point[i] >= self.mins[i] && point[i] <= self.maxs[i])
+    }
+
+    pub fn expand_point(&mut self, point: &Vec3d) {
+        for i in 0..3 {
+            self.mins[i] = self.mins[i].min(point[i]);
+            self.maxs[i] = self.maxs[i].max(point[i]);
+        }
+    }
+
+    pub fn expand_points(&mut self, points: &[Vec3d]) {
+        for point in points {
+            self.expand_point(point);
+        }
+    }
+
+    pub fn expand_bounds(&mut self, other: &Aabb) {
+        self.expand_point(&other.mins);
+        self.expand_point(&other.maxs);
+    }
+
+    pub fn corners(&self) -> [Vec3d; 8] {
+        let mut corners: [Vec3d; 8] = [Vec3d::zero(); 8];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            *corner = Vec3d::new(
+                if i & 1 == 0 { self.mins.x } else { self.maxs.x },
+                if i & 2 == 0 { self.mins.y } else { self.maxs.y },
+                if i & 4 == 0 { self.mins.z } else { self.maxs.z }
+            );
+        }
+        corners
+    }
+
+    pub fn contains(&self, other: &Aabb) -> bool {
+        self.contains_point(&other.mins) && self.contains_point(&other.maxs)
+    }
+
+    /// Smallest bounds containing both
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        let mut bounds: Aabb = *self;
+        bounds.expand_bounds(other);
+        bounds
+    }
+
+    /// Grows the bounds by margin on every side
+    pub fn fattened(&self, margin: f64) -> Aabb {
+        let extent: Vec3d = Vec3d::new(margin, margin, margin);
+        Aabb::new(self.mins - extent, self.maxs + extent)
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let (x, y, z) = (self.width_x(), self.width_y(), self.width_z());
+        2.0 * (x * y + y * z + z * x)
+    }
+
+    /// Slab test, returns the distance along the ray where it enters the bounds.
+    /// A ray starting inside the bounds hits them at 0, dir is expected to be normalized.
+    pub fn intersect_ray(&self, origin: &Vec3d, dir: &Vec3d, max_dist: f64) -> Option<f64> {
+        let mut t_min: f64 = 0.0;
+        let mut t_max: f64 = max_dist;
+
+        for i in 0..3 {
+            if f64::abs(dir[i]) < f64::EPSILON {
+                // Parallel to the slab, the ray has to start between its planes
+                if origin[i] < self.mins[i] || origin[i] > self.maxs[i] {
+                    return None;
+                }
+                continue;
+            }
+
+            let inv_dir: f64 = 1.0 / dir[i];
+            let t1: f64 = (self.mins[i] - origin[i]) * inv_dir;
+            let t2: f64 = (self.maxs[i] - origin[i]) * inv_dir;
+            t_min = t_min.max(t1.min(t2));
+            t_max = t_max.min(t1.max(t2));
+
+            if t_min > t_max {
+                return None;
+            }
+        }
+
+        Some(t_min)
+    }
+
+    pub fn width_x(&self) -> f64 {
+        self.maxs.x - self.mins.x
+    }
+
+    pub fn width_y(&self) -> f64 {
+        self.maxs.y - self.mins.y
+    }
+
+    pub fn width_z(&self) -> f64 {
+        self.maxs.z - self.mins.z
+    }
+}
@@ -0,0 +1,384 @@
+use crate::physics::body::Body;
+use crate::physics::bounds::Aabb;
+use crate::physics::broadphase::{
+    swept_bounds,
+    Broadphase,
+    CollisionPair
+};
+use crate::physics::scene::BodyHandle;
+use crate::physics::vec::vec3d::Vec3d;
+
+const NULL_NODE: usize = usize::MAX;
+
+#[derive(Debug, Clone, Copy)]
+struct TreeNode {
+    bounds: Aabb, // Fattened for leaves, the union of the children otherwise
+    parent: usize, // Next free node while the node is on the free list
+    child1: usize,
+    child2: usize,
+    height: i32, // Leaves are 0, free nodes are -1
+    body: Option<BodyHandle>
+}
+
+impl TreeNode {
+    fn is_leaf(&self) -> bool {
+        self.child1 == NULL_NODE
+    }
+}
+
+/// Bounding volume hierarchy over the bodies' bounds.
+/// Leaves are fattened by a margin, so a body only moves in the tree once it leaves its fat bounds.
+/// Inserting and removing leaves keeps the tree balanced with rotations.
+pub struct DynamicAabbTree {
+    nodes: Vec<TreeNode>,
+    root: usize,
+    free_list: usize,
+    leaves: Vec<usize>, // Leaf node of each body, indexed by handle
+    bounds: Vec<Option<Aabb>>, // Tight swept bounds of each body as of the last update
+    margin: f64
+}
+
+impl DynamicAabbTree {
+    pub const DEFAULT_MARGIN: f64 = 0.1;
+
+    pub fn new() -> Self {
+        DynamicAabbTree::with_margin(DynamicAabbTree::DEFAULT_MARGIN)
+    }
+
+    pub fn with_margin(margin: f64) -> Self {
+        DynamicAabbTree {
+            nodes: Vec::new(),
+            root: NULL_NODE,
+            free_list: NULL_NODE,
+            leaves: Vec::new(),
+            bounds: Vec::new(),
+            margin
+        }
+    }
+
+    /// Height of the tree, a balanced tree of n leaves is about log2(n) high
+    pub fn height(&self) -> i32 {
+        if self.root == NULL_NODE {
+            return 0;
+        }
+        self.nodes[self.root].height
+    }
+
+    fn allocate_node(&mut self) -> usize {
+        let node = TreeNode {
+            bounds: Aabb::empty(),
+            parent: NULL_NODE,
+            child1: NULL_NODE,
+            child2: NULL_NODE,
+            height: 0,
+            body: None
+        };
+
+        if self.free_list == NULL_NODE {
+            self.nodes.push(node);
+            return self.nodes.len() - 1;
+        }
+
+        let index: usize = self.free_list;
+        self.free_list = self.nodes[index].parent;
+        self.nodes[index] = node;
+        index
+    }
+
+    fn free_node(&mut self, index: usize) {
+        self.nodes[index].parent = self.free_list;
+        self.nodes[index].height = -1;
+        self.nodes[index].body = None;
+        self.free_list = index;
+    }
+
+    fn create_leaf(&mut self, handle: BodyHandle, bounds: &Aabb) {
+        let leaf: usize = self.allocate_node();
+        self.nodes[leaf].bounds = bounds.fattened(self.margin);
+        self.nodes[leaf].body = Some(handle);
+        self.insert_leaf(leaf);
+
+        if self.leaves.len() <= handle.0 {
+            self.leaves.resize(handle.0 + 1, NULL_NODE);
+        }
+        self.leaves[handle.0] = leaf;
+    }
+
+    fn destroy_leaf(&mut self, handle: BodyHandle) {
+        let leaf: usize = self.leaves[handle.0];
+        self.remove_leaf(leaf);
+        self.free_node(leaf);
+        self.leaves[handle.0] = NULL_NODE;
+    }
+
+    fn insert_leaf(&mut self, leaf: usize) {
+        if self.root == NULL_NODE {
+            self.root = leaf;
+            self.nodes[leaf].parent = NULL_NODE;
+            return;
+        }
+
+        // Find the best sibling by the surface area heuristic
+        let leaf_bounds: Aabb = self.nodes[leaf].bounds;
+        let mut index: usize = self.root;
+        while !self.nodes[index].is_leaf() {
+            let node: TreeNode = self.nodes[index];
+            let area: f64 = node.bounds.surface_area();
+            let combined_area: f64 = node.bounds.union(&leaf_bounds).surface_area();
+
+            // Cost of creating a new parent for this node and the new leaf
+            let cost: f64 = 2.0 * combined_area;
+
+            // Minimum cost of pushing the leaf further down the tree
+            let inheritance_cost: f64 = 2.0 * (combined_area - area);
+            let descend_cost = |child: usize| -> f64 {
+                let child_node: &TreeNode = &self.nodes[child];
+                let union_area: f64 = child_node.bounds.union(&leaf_bounds).surface_area();
+                if child_node.is_leaf() {
+                    union_area + inheritance_cost
+                } else {
+                    union_area - child_node.bounds.surface_area() + inheritance_cost
+                }
+            };
+            let cost1: f64 = descend_cost(node.child1);
+            let cost2: f64 = descend_cost(node.child2);
+
+            if cost < cost1 && cost < cost2 {
+                break;
+            }
+            index = if cost1 < cost2 { node.child1 } else { node.child2 };
+        }
+        let sibling: usize = index;
+
+        // Create a new parent for the sibling and the leaf
+        let old_parent: usize = self.nodes[sibling].parent;
+        let new_parent: usize = self.allocate_node();
+        self.nodes[new_parent].parent = old_parent;
+        self.nodes[new_parent].bounds = leaf_bounds.union(&self.nodes[sibling].bounds);
+        self.nodes[new_parent].height = self.nodes[sibling].height + 1;
+        self.nodes[new_parent].child1 = sibling;
+        self.nodes[new_parent].child2 = leaf;
+        self.nodes[sibling].parent = new_parent;
+        self.nodes[leaf].parent = new_parent;
+
+        if old_parent == NULL_NODE {
+            self.root = new_parent;
+        } else if self.nodes[old_parent].child1 == sibling {
+            self.nodes[old_parent].child1 = new_parent;
+        } else {
+            self.nodes[old_parent].child2 = new_parent;
+        }
+
+        // Walk back up the tree fixing heights and bounds
+        self.refit_ancestors(self.nodes[leaf].parent);
+    }
+
+    fn remove_leaf(&mut self, leaf: usize) {
+        if leaf == self.root {
+            self.root = NULL_NODE;
+            return;
+        }
+
+        let parent: usize = self.nodes[leaf].parent;
+        let grand_parent: usize = self.nodes[parent].parent;
+        let sibling: usize = if self.nodes[parent].child1 == leaf {
+            self.nodes[parent].child2
+        } else {
+            self.nodes[parent].child1
+        };
+
+        // The sibling takes the place of the parent
+        self.nodes[sibling].parent = grand_parent;
+        self.free_node(parent);
+
+        if grand_parent == NULL_NODE {
+            self.root = sibling;
+            return;
+        }
+
+        if self.nodes[grand_parent].child1 == parent {
+            self.nodes[grand_parent].child1 = sibling;
+        } else {
+            self.nodes[grand_parent].child2 = sibling;
+        }
+        self.refit_ancestors(grand_parent);
+    }
+
+    fn refit_ancestors(&mut self, mut index: usize) {
+        while index != NULL_NODE {
+            index = self.balance(index);
+
+            let (child1, child2) = (self.nodes[index].child1, self.nodes[index].child2);
+            self.nodes[index].height = 1 + self.nodes[child1].height.max(self.nodes[child2].height);
+            self.nodes[index].bounds = self.nodes[child1].bounds.union(&self.nodes[child2].bounds);
+
+            index = self.nodes[index].parent;
+        }
+    }
+
+    /// Rotates the higher child up if node a is imbalanced, returns the index of the new subtree root
+    fn balance(&mut self, a: usize) -> usize {
+        if self.nodes[a].is_leaf() || self.nodes[a].height < 2 {
+            return a;
+        }
+
+        let (b, c) = (self.nodes[a].child1, self.nodes[a].child2);
+        let balance: i32 = self.nodes[c].height - self.nodes[b].height;
+
+        if balance > 1 {
+            self.rotate_up(a, c, b, false);
+            return c;
+        }
+
+        if balance < -1 {
+            self.rotate_up(a, b, c, true);
+            return b;
+        }
+
+        a
+    }
+
+    /// Makes `up`, a child of `a`, the parent of `a`.
+    /// `other` is a's other child, `up_is_child1` tells which slot of a held `up`.
+    fn rotate_up(&mut self, a: usize, up: usize, other: usize, up_is_child1: bool) {
+        let (f, g) = (self.nodes[up].child1, self.nodes[up].child2);
+
+        // Swap a and up
+        self.nodes[up].child1 = a;
+        self.nodes[up].parent = self.nodes[a].parent;
+        self.nodes[a].parent = up;
+
+        let up_parent: usize = self.nodes[up].parent;
+        if up_parent == NULL_NODE {
+            self.root = up;
+        } else if self.nodes[up_parent].child1 == a {
+            self.nodes[up_parent].child1 = up;
+        } else {
+            self.nodes[up_parent].child2 = up;
+        }
+
+        // The higher grandchild stays with up, the lower one moves under a
+        let (keep, give) = if self.nodes[f].height > self.nodes[g].height { (f, g) } else { (g, f) };
+        self.nodes[up].child2 = keep;
+        if up_is_child1 {
+            self.nodes[a].child1 = give;
+        } else {
+            self.nodes[a].child2 = give;
+        }
+        self.nodes[give].parent = a;
+
+        self.nodes[a].bounds = self.nodes[other].bounds.union(&self.nodes[give].bounds);
+        self.nodes[up].bounds = self.nodes[a].bounds.union(&self.nodes[keep].bounds);
+        self.nodes[a].height = 1 + self.nodes[other].height.max(self.nodes[give].height);
+        self.nodes[up].height = 1 + self.nodes[a].height.max(self.nodes[keep].height);
+    }
+
+    /// Calls visit for every leaf whose fat bounds pass the node test
+    fn traverse(&self, mut node_test: impl FnMut(&Aabb) -> bool, mut visit: impl FnMut(BodyHandle)) {
+        if self.root == NULL_NODE {
+            return;
+        }
+
+        let mut stack: Vec<usize> = vec![self.root];
+        while let Some(index) = stack.pop() {
+            let node: &TreeNode = &self.nodes[index];
+            if !node_test(&node.bounds) {
+                continue;
+            }
+
+            match node.body {
+                Some(handle) => visit(handle),
+                None => {
+                    stack.push(node.child1);
+                    stack.push(node.child2);
+                }
+            }
+        }
+    }
+}
+
+impl Broadphase for DynamicAabbTree {
+    fn find_pairs(&mut self, bodies: &[Option<Body>], dt_sec: f64) -> Vec<CollisionPair> {
+        self.bounds.clear();
+
+        for (i, body) in bodies.iter().enumerate() {
+            let handle: BodyHandle = BodyHandle(i);
+            let in_tree: bool = self.leaves.get(i).is_some_and(|&leaf| leaf != NULL_NODE);
+
+            let Some(body) = body else {
+                // The body was removed from the scene
+                if in_tree {
+                    self.destroy_leaf(handle);
+                }
+                self.bounds.push(None);
+                continue;
+            };
+
+            let bounds: Aabb = swept_bounds(body, dt_sec);
+            self.bounds.push(Some(bounds));
+
+            if !in_tree {
+                self.create_leaf(handle, &bounds);
+            } else if !self.nodes[self.leaves[i]].bounds.contains(&bounds) {
+                // The body left its fat bounds, reinsert it around its new bounds
+                let leaf: usize = self.leaves[i];
+                self.remove_leaf(leaf);
+                self.nodes[leaf].bounds = bounds.fattened(self.margin);
+                self.insert_leaf(leaf);
+            }
+        }
+
+        // Each leaf queries the tree, a pair is only kept from its lower handle
+        let mut pairs: Vec<CollisionPair> = Vec::new();
+        for (i, bounds_a) in self.bounds.iter().enumerate() {
+            let Some(bounds_a) = bounds_a else { continue };
+            self.traverse(
+                |node_bounds| node_bounds.does_intersect(bounds_a),
+                |handle| {
+                    if handle.0 <= i {
+                        return;
+                    }
+                    if self.bounds[handle.0].is_some_and(|bounds_b| bounds_b.does_intersect(bounds_a)) {
+                        pairs.push(CollisionPair::new(BodyHandle(i), handle));
+                    }
+                }
+            );
+        }
+
+        pairs
+    }
+
+    fn query_bounds(&self, bounds: &Aabb) -> Vec<BodyHandle> {
+        let mut handles: Vec<BodyHandle> = Vec::new();
+        self.traverse(
+            |node_bounds| node_bounds.does_intersect(bounds),
+            |handle| {
+                if self.bounds[handle.0].is_some_and(|body_bounds| body_bounds.does_intersect(bounds)) {
+                    handles.push(handle);
+                }
+            }
+        );
+        handles
+    }
+
+    fn query_ray(&self, origin: &Vec3d, dir: &Vec3d, max_dist: f64) -> Vec<BodyHandle> {
+        let mut handles: Vec<BodyHandle> = Vec::new();
+        self.traverse(
+            |node_bounds| node_bounds.intersect_ray(origin, dir, max_dist).is_some(),
+            |handle| {
+                let hit: bool = self.bounds[handle.0]
+                    .is_some_and(|body_bounds| body_bounds.intersect_ray(origin, dir, max_dist).is_some());
+                if hit {
+                    handles.push(handle);
+                }
+            }
+        );
+        handles
+    }
+}
+
+impl Default for DynamicAabbTree {
+    fn default() -> Self {
+        DynamicAabbTree::new()
+    }
+}
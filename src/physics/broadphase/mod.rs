@@ -0,0 +1,9 @@
+pub mod pairs;
+pub mod sweep_and_prune;
+pub mod dynamic_tree;
+
+pub use pairs::{
+    swept_bounds,
+    Broadphase,
+    CollisionPair
+};
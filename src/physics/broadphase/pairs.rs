@@ -0,0 +1,51 @@
+use crate::physics::body::Body;
+use crate::physics::bounds::Aabb;
+use crate::physics::scene::BodyHandle;
+use crate::physics::shape::Shape;
+use crate::physics::vec::vec3d::Vec3d;
+
+const BOUNDS_EPSILON: f64 = 0.01;
+
+/// Two bodies whose bounds overlap, to be checked by the narrowphase
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CollisionPair {
+    pub a: BodyHandle,
+    pub b: BodyHandle
+}
+
+impl CollisionPair {
+    /// Orders the handles so the same two bodies always make the same pair
+    pub fn new(first: BodyHandle, second: BodyHandle) -> Self {
+        if first.0 <= second.0 {
+            CollisionPair { a: first, b: second }
+        } else {
+            CollisionPair { a: second, b: first }
+        }
+    }
+}
+
+/// Finds the body pairs worth handing to the narrowphase.
+/// Bodies are indexed by their handle, removed bodies are None.
+pub trait Broadphase: Send + Sync {
+    /// Refreshes the bounds of every body and returns the pairs whose bounds overlap
+    fn find_pairs(&mut self, bodies: &[Option<Body>], dt_sec: f64) -> Vec<CollisionPair>;
+
+    /// Bodies whose bounds overlap the given bounds, as of the last `find_pairs`
+    fn query_bounds(&self, bounds: &Aabb) -> Vec<BodyHandle>;
+
+    /// Bodies whose bounds are hit by the ray within max_dist, as of the last `find_pairs`.
+    /// dir is expected to be normalized.
+    fn query_ray(&self, origin: &Vec3d, dir: &Vec3d, max_dist: f64) -> Vec<BodyHandle>;
+}
+
+/// Bounds of the body over the whole step, so fast bodies still find what they will hit
+pub fn swept_bounds(body: &Body, dt_sec: f64) -> Aabb {
+    let mut bounds: Aabb = body.shape.get_bounds(&body.position, &body.orientation);
+
+    let displacement: Vec3d = body.linear_velocity * dt_sec;
+    bounds.expand_point(&(bounds.mins + displacement));
+    bounds.expand_point(&(bounds.maxs + displacement));
+
+    // Expand by a small margin so resting contacts are found
+    bounds.fattened(BOUNDS_EPSILON)
+}
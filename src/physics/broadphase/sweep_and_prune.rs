@@ -0,0 +1,118 @@
+use crate::physics::body::Body;
+use crate::physics::bounds::Aabb;
+use crate::physics::broadphase::{
+    swept_bounds,
+    Broadphase,
+    CollisionPair
+};
+use crate::physics::scene::BodyHandle;
+use crate::physics::vec::vec3d::Vec3d;
+
+/// One end of a body's bounds projected on the sweep axis
+#[derive(Debug, Clone, Copy)]
+struct PseudoBody {
+    handle: BodyHandle,
+    value: f64,
+    is_min: bool
+}
+
+/// Sorts the bounds of every body along one axis,
+/// bodies whose intervals overlap on it become candidate pairs
+pub struct SweepAndPrune {
+    axis: Vec3d,
+    pseudo_bodies: Vec<PseudoBody>,
+    bounds: Vec<Option<Aabb>>
+}
+
+impl SweepAndPrune {
+    pub fn new() -> Self {
+        SweepAndPrune {
+            // Sweeping along the diagonal avoids degenerating when bodies line up on a world axis
+            axis: Vec3d::new(1.0, 1.0, 1.0).normalize(),
+            pseudo_bodies: Vec::new(),
+            bounds: Vec::new()
+        }
+    }
+
+    fn sort_bodies_bounds(&mut self, bodies: &[Option<Body>], dt_sec: f64) {
+        self.pseudo_bodies.clear();
+        self.bounds.clear();
+
+        for (i, body) in bodies.iter().enumerate() {
+            let Some(body) = body else {
+                self.bounds.push(None);
+                continue;
+            };
+
+            let bounds: Aabb = swept_bounds(body, dt_sec);
+            self.bounds.push(Some(bounds));
+
+            self.pseudo_bodies.push(PseudoBody { handle: BodyHandle(i), value: self.axis.dot(bounds.mins), is_min: true });
+            self.pseudo_bodies.push(PseudoBody { handle: BodyHandle(i), value: self.axis.dot(bounds.maxs), is_min: false });
+        }
+
+        self.pseudo_bodies.sort_by(|a, b| a.value.total_cmp(&b.value));
+    }
+
+    fn build_pairs(&self) -> Vec<CollisionPair> {
+        let mut pairs: Vec<CollisionPair> = Vec::new();
+
+        for (i, a) in self.pseudo_bodies.iter().enumerate() {
+            if !a.is_min {
+                continue;
+            }
+
+            // Every body that starts before A ends overlaps it on the axis
+            for b in &self.pseudo_bodies[i + 1..] {
+                if b.handle == a.handle {
+                    break;
+                }
+                if !b.is_min {
+                    continue;
+                }
+
+                // Prune pairs that overlap on the axis but not in space
+                if let (Some(bounds_a), Some(bounds_b)) = (&self.bounds[a.handle.0], &self.bounds[b.handle.0]) {
+                    if bounds_a.does_intersect(bounds_b) {
+                        pairs.push(CollisionPair::new(a.handle, b.handle));
+                    }
+                }
+            }
+        }
+
+        pairs
+    }
+}
+
+impl Broadphase for SweepAndPrune {
+    fn find_pairs(&mut self, bodies: &[Option<Body>], dt_sec: f64) -> Vec<CollisionPair> {
+        self.sort_bodies_bounds(bodies, dt_sec);
+        self.build_pairs()
+    }
+
+    fn query_bounds(&self, bounds: &Aabb) -> Vec<BodyHandle> {
+        self.bounds
+            .iter()
+            .enumerate()
+            .filter(|(_, body_bounds)| body_bounds.is_some_and(|body_bounds| body_bounds.does_intersect(bounds)))
+            .map(|(i, _)| BodyHandle(i))
+            .collect()
+    }
+
+    fn query_ray(&self, origin: &Vec3d, dir: &Vec3d, max_dist: f64) -> Vec<BodyHandle> {
+        self.bounds
+            .iter()
+            .enumerate()
+            .filter(|(_, body_bounds)| {
+                body_bounds.is_some_and(|body_bounds| body_bounds.intersect_ray(origin, dir, max_dist).is_some())
+            })
+            .map(|(i, _)| BodyHandle(i))
+            .collect()
+    }
+}
+
+impl Default for SweepAndPrune {
+    fn default() -> Self {
+        SweepAndPrune::new()
+    }
+}
@@ -0,0 +1,150 @@
+use crate::physics::body::Body;
+use crate::physics::constraints::penetration::PenetrationConstraint;
+use crate::physics::mat::mat3::Mat3;
+use crate::physics::scene::BodyHandle;
+use crate::physics::vec::vec3d::Vec3d;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Contact {
+    pub pt_on_a_world_space: Vec3d,
+    pub pt_on_b_world_space: Vec3d,
+    pub pt_on_a_local_space: Vec3d,
+    pub pt_on_b_local_space: Vec3d,
+
+    pub normal: Vec3d, // In world space, points from A to B
+    pub separation_distance: f64, // Positive when non-penetrating, negative when penetrating
+    pub time_of_impact: f64,
+    pub from_ccd: bool, // Found ahead of time by a sweep, the bodies only meet at time_of_impact
+
+    pub body_a: BodyHandle,
+    pub body_b: BodyHandle
+}
+
+impl Contact {
+    /// The same contact seen from body B
+    pub fn swapped(&self) -> Contact {
+        Contact {
+            pt_on_a_world_space: self.pt_on_b_world_space,
+            pt_on_b_world_space: self.pt_on_a_world_space,
+            pt_on_a_local_space: self.pt_on_b_local_space,
+            pt_on_b_local_space: self.pt_on_a_local_space,
+            normal: -self.normal,
+            separation_distance: self.separation_distance,
+            time_of_impact: self.time_of_impact,
+            from_ccd: self.from_ccd,
+            body_a: self.body_b,
+            body_b: self.body_a
+        }
+    }
+}
+
+pub fn resolve_contact(contact: &Contact, body_a: &mut Body, body_b: &mut Body) {
+    resolve_manifold(std::slice::from_ref(contact), body_a, body_b);
+}
+
+/// Resolves every point of a manifold between the same two bodies.
+/// Impulses are applied per point, but the bodies are only pushed apart once, by the deepest point.
+/// The bodies are expected to be at the manifold's time of impact.
+pub fn resolve_manifold(manifold: &[Contact], body_a: &mut Body, body_b: &mut Body) {
+    // Two bodies with infinite mass can't push each other
+    if body_a.get_inv_mass() + body_b.get_inv_mass() == 0.0 {
+        return;
+    }
+
+    for contact in manifold {
+        apply_contact_impulse(contact, body_a, body_b);
+    }
+
+    let deepest: Option<&Contact> = manifold
+        .iter()
+        .min_by(|a, b| a.separation_distance.total_cmp(&b.separation_distance));
+
+    // Contacts found ahead of time are touching, not penetrating
+    if let Some(contact) = deepest.filter(|contact| !contact.from_ccd) {
+        separate_bodies(contact, body_a, body_b);
+    }
+}
+
+fn apply_contact_impulse(contact: &Contact, body_a: &mut Body, body_b: &mut Body) {
+    // The bodies may have moved since the contact was found, the local points moved with them
+    let pt_on_a: Vec3d = body_a.body_space_to_world_space(&contact.pt_on_a_local_space);
+    let pt_on_b: Vec3d = body_b.body_space_to_world_space(&contact.pt_on_b_local_space);
+
+    let inv_mass_a: f64 = body_a.get_inv_mass();
+    let inv_mass_b: f64 = body_b.get_inv_mass();
+
+    let elasticity: f64 = body_a.material.combine_restitution(&body_b.material);
+
+    let inv_inertia_world_a: Mat3 = body_a.get_inverse_inertia_tensor_world_space();
+    let inv_inertia_world_b: Mat3 = body_b.get_inverse_inertia_tensor_world_space();
+
+    let n: Vec3d = contact.normal;
+
+    let ra: Vec3d = pt_on_a - body_a.get_center_of_mass_world_space();
+    let rb: Vec3d = pt_on_b - body_b.get_center_of_mass_world_space();
+
+    let angular_j_a: Vec3d = (inv_inertia_world_a * ra.cross(n)).cross(ra);
+    let angular_j_b: Vec3d = (inv_inertia_world_b * rb.cross(n)).cross(rb);
+    let angular_factor: f64 = (angular_j_a + angular_j_b).dot(n);
+
+    // Get the world space velocity of the motion and rotation
+    let vel_a: Vec3d = body_a.linear_velocity + body_a.angular_velocity.cross(ra);
+    let vel_b: Vec3d = body_b.linear_velocity + body_b.angular_velocity.cross(rb);
+
+    // Calculate the collision impulse
+    // J = (1 + e) * (vA - vB) . n / (1/mA + 1/mB + angular factor)
+    let vab: Vec3d = vel_a - vel_b;
+    let impulse_j: f64 = (1.0 + elasticity) * vab.dot(n) / (inv_mass_a + inv_mass_b + angular_factor);
+
+    // The bodies are already moving apart, pushing them would pull them together
+    if impulse_j <= 0.0 {
+        return;
+    }
+
+    let vec_impulse_j: Vec3d = n * impulse_j;
+    body_a.apply_impulse(pt_on_a, -vec_impulse_j);
+    body_b.apply_impulse(pt_on_b, vec_impulse_j);
+
+    // Coulomb friction against the sliding left after the push, at most mu times the push
+    let vel_a: Vec3d = body_a.linear_velocity + body_a.angular_velocity.cross(ra);
+    let vel_b: Vec3d = body_b.linear_velocity + body_b.angular_velocity.cross(rb);
+    let vab: Vec3d = vel_a - vel_b;
+    let vel_tangent: Vec3d = vab - n * vab.dot(n);
+    let sliding_speed: f64 = vel_tangent.get_magnitude();
+    if sliding_speed < f64::EPSILON {
+        return;
+    }
+
+    let friction: f64 = if sliding_speed < PenetrationConstraint::STATIC_FRICTION_SPEED {
+        body_a.material.combine_static_friction(&body_b.material)
+    } else {
+        body_a.material.combine_dynamic_friction(&body_b.material)
+    };
+
+    // Impulse that would stop the sliding, J_t = |v_t| / (1/mA + 1/mB + angular factor along t)
+    let t: Vec3d = vel_tangent / sliding_speed;
+    let angular_t_a: Vec3d = (inv_inertia_world_a * ra.cross(t)).cross(ra);
+    let angular_t_b: Vec3d = (inv_inertia_world_b * rb.cross(t)).cross(rb);
+    let angular_factor_t: f64 = (angular_t_a + angular_t_b).dot(t);
+    let impulse_t: f64 = f64::min(sliding_speed / (inv_mass_a + inv_mass_b + angular_factor_t), friction * impulse_j);
+
+    let vec_impulse_t: Vec3d = t * impulse_t;
+    body_a.apply_impulse(pt_on_a, -vec_impulse_t);
+    body_b.apply_impulse(pt_on_b, vec_impulse_t);
+}
+
+fn separate_bodies(contact: &Contact, body_a: &mut Body, body_b: &mut Body) {
+    let inv_mass_a: f64 = body_a.get_inv_mass();
+    let inv_mass_b: f64 = body_b.get_inv_mass();
+
+    // Move colliding objects to just outside of each other,
+    // the lighter body moves further
+    let t_a: f64 = inv_mass_a / (inv_mass_a + inv_mass_b);
+    let t_b: f64 = inv_mass_b / (inv_mass_a + inv_mass_b);
+
+    let pt_on_a: Vec3d = body_a.body_space_to_world_space(&contact.pt_on_a_local_space);
+    let pt_on_b: Vec3d = body_b.body_space_to_world_space(&contact.pt_on_b_local_space);
+    let ds: Vec3d = pt_on_b - pt_on_a;
+    body_a.position += ds * t_a;
+    body_b.position -= ds * t_b;
+}
@@ -0,0 +1,360 @@
+use crate::physics::body::Body;
+use crate::physics::collision::contact::Contact;
+use crate::physics::collision::gjk::{
+    gjk_intersection_simplex,
+    signed_volume_2d,
+    support,
+    ConvexSupport,
+    SupportPoint
+};
+use crate::physics::scene::BodyHandle;
+use crate::physics::vec::vec3d::Vec3d;
+
+// Both bodies are grown by this much, so that touching faces still overlap and give GJK a simplex
+const EPA_BIAS: f64 = 0.001;
+const MAX_EPA_ITERATIONS: usize = 64;
+// The polytope stops expanding once a new support point is this close to the closest face
+const EPA_TOLERANCE: f64 = 1e-6;
+// Simplex points closer than this to the line or plane of the others don't add a dimension
+const DEGENERATE_EPSILON: f64 = 1e-6;
+
+/// Face of the polytope, wound so that its normal points away from the origin
+#[derive(Debug, Clone, Copy)]
+struct Triangle {
+    a: usize,
+    b: usize,
+    c: usize
+}
+
+/// Deepest points of two overlapping convex sets, in world space
+#[derive(Debug, Clone, Copy)]
+pub struct EpaResult {
+    pub pt_on_a: Vec3d,
+    pub pt_on_b: Vec3d,
+    pub normal: Vec3d, // Points from A to B, moving B along it by depth separates the sets
+    pub depth: f64
+}
+
+/// Penetration between two overlapping convex bodies, using the Expanding Polytope Algorithm.
+/// The normal points from A to B and the separation distance is minus the penetration depth.
+/// Returns None when the bodies are apart.
+pub fn epa(handle_a: BodyHandle, body_a: &Body, handle_b: BodyHandle, body_b: &Body) -> Option<Contact> {
+    let result: EpaResult = epa_penetration(body_a, body_b)?;
+
+    Some(Contact {
+        pt_on_a_world_space: result.pt_on_a,
+        pt_on_b_world_space: result.pt_on_b,
+        pt_on_a_local_space: body_a.world_space_to_body_space(&result.pt_on_a),
+        pt_on_b_local_space: body_b.world_space_to_body_space(&result.pt_on_b),
+        normal: result.normal,
+        separation_distance: -result.depth,
+        time_of_impact: 0.0,
+        from_ccd: false,
+        body_a: handle_a,
+        body_b: handle_b
+    })
+}
+
+/// Penetration between any two overlapping convex sets, None when they are apart
+pub fn epa_penetration(body_a: &impl ConvexSupport, body_b: &impl ConvexSupport) -> Option<EpaResult> {
+    let simplex: Vec<SupportPoint> = gjk_intersection_simplex(body_a, body_b, EPA_BIAS)?;
+    let simplex: Vec<SupportPoint> = complete_tetrahedron(body_a, body_b, simplex)?;
+
+    let (biased_pt_on_a, biased_pt_on_b) = epa_expand(body_a, body_b, simplex);
+
+    // The closest point of the Minkowski difference to the origin is the shortest way out
+    let delta: Vec3d = biased_pt_on_a - biased_pt_on_b;
+    let normal: Vec3d = if delta.get_magnitude_squared() > 0.0 {
+        delta.normalize()
+    } else {
+        (body_b.get_center() - body_a.get_center()).normalize()
+    };
+
+    // Remove the bias from the points and the depth
+    let pt_on_a: Vec3d = biased_pt_on_a - normal * EPA_BIAS;
+    let pt_on_b: Vec3d = biased_pt_on_b + normal * EPA_BIAS;
+    let depth: f64 = delta.get_magnitude() - 2.0 * EPA_BIAS;
+
+    Some(EpaResult {
+        pt_on_a,
+        pt_on_b,
+        normal,
+        depth
+    })
+}
+
+/// GJK stops as soon as the origin is on its simplex, which can be a point, a segment or a triangle.
+/// EPA needs a volume to expand, so add support points until the simplex is a tetrahedron.
+/// Returns None when the Minkowski difference is flat.
+fn complete_tetrahedron(body_a: &impl ConvexSupport, body_b: &impl ConvexSupport,
+                        mut simplex: Vec<SupportPoint>) -> Option<Vec<SupportPoint>> {
+    if simplex.len() == 1 {
+        let axes: [Vec3d; 6] = [
+            Vec3d::new(1.0, 0.0, 0.0), Vec3d::new(0.0, 1.0, 0.0), Vec3d::new(0.0, 0.0, 1.0),
+            Vec3d::new(-1.0, 0.0, 0.0), Vec3d::new(0.0, -1.0, 0.0), Vec3d::new(0.0, 0.0, -1.0)
+        ];
+        let new_pt: SupportPoint = axes
+            .iter()
+            .map(|axis| support(body_a, body_b, *axis, EPA_BIAS))
+            .find(|pt| !simplex.contains(pt))?;
+        simplex.push(new_pt);
+    }
+
+    if simplex.len() == 2 {
+        // Search perpendicular to the segment
+        let ab: Vec3d = (simplex[1].xyz - simplex[0].xyz).normalize();
+        let mut u: Vec3d = Vec3d::zero();
+        let mut v: Vec3d = Vec3d::zero();
+        ab.get_ortho(&mut u, &mut v);
+
+        let new_pt: SupportPoint = [u, v, -u, -v]
+            .iter()
+            .map(|dir| support(body_a, body_b, *dir, EPA_BIAS))
+            .find(|pt| {
+                let ap: Vec3d = pt.xyz - simplex[0].xyz;
+                (ap - ab * ap.dot(ab)).get_magnitude() > DEGENERATE_EPSILON
+            })?;
+        simplex.push(new_pt);
+    }
+
+    if simplex.len() == 3 {
+        // Search along the normal of the triangle, on either side
+        let normal: Vec3d = (simplex[1].xyz - simplex[0].xyz).cross(simplex[2].xyz - simplex[0].xyz).normalize();
+
+        let new_pt: SupportPoint = [normal, -normal]
+            .iter()
+            .map(|dir| support(body_a, body_b, *dir, EPA_BIAS))
+            .find(|pt| f64::abs((pt.xyz - simplex[0].xyz).dot(normal)) > DEGENERATE_EPSILON)?;
+        simplex.push(new_pt);
+    }
+
+    Some(simplex)
+}
+
+/// Grows the polytope towards the boundary of the Minkowski difference, until the face closest to the origin
+/// can't be pushed out anymore. Returns the points of A and B that project to the origin on that face.
+fn epa_expand(body_a: &impl ConvexSupport, body_b: &impl ConvexSupport, simplex: Vec<SupportPoint>) -> (Vec3d, Vec3d) {
+    let mut points: Vec<SupportPoint> = simplex;
+    let mut triangles: Vec<Triangle> = vec![
+        Triangle { a: 0, b: 1, c: 2 },
+        Triangle { a: 0, b: 2, c: 3 },
+        Triangle { a: 2, b: 1, c: 3 },
+        Triangle { a: 1, b: 0, c: 3 }
+    ];
+
+    // Make the faces of the tetrahedron point outwards, the new faces keep the winding of the removed ones
+    let center: Vec3d = (points[0].xyz + points[1].xyz + points[2].xyz + points[3].xyz) * 0.25;
+    for triangle in triangles.iter_mut() {
+        if signed_distance_to_triangle(triangle, &center, &points) > 0.0 {
+            std::mem::swap(&mut triangle.b, &mut triangle.c);
+        }
+    }
+
+    for _ in 0..MAX_EPA_ITERATIONS {
+        let closest: Triangle = closest_triangle(&triangles, &points);
+        let normal: Vec3d = triangle_normal(&closest, &points);
+        let new_pt: SupportPoint = support(body_a, body_b, normal, EPA_BIAS);
+
+        // The point is already on the polytope, it can't grow any further in this direction
+        if points.contains(&new_pt) {
+            break;
+        }
+
+        // The closest face is on the boundary of the Minkowski difference
+        if signed_distance_to_triangle(&closest, &new_pt.xyz, &points) <= EPA_TOLERANCE {
+            break;
+        }
+
+        let new_idx: usize = points.len();
+        points.push(new_pt);
+
+        // Remove the faces that can see the new point, and close the hole with faces to it
+        let (removed, kept): (Vec<Triangle>, Vec<Triangle>) = triangles
+            .into_iter()
+            .partition(|triangle| signed_distance_to_triangle(triangle, &new_pt.xyz, &points) > 0.0);
+        triangles = kept;
+
+        for (a, b) in dangling_edges(&removed) {
+            triangles.push(Triangle { a, b, c: new_idx });
+        }
+    }
+
+    // Project the origin onto the closest face
+    let closest: Triangle = closest_triangle(&triangles, &points);
+    let (pt_a, pt_b, pt_c) = (points[closest.a], points[closest.b], points[closest.c]);
+    let lambdas: Vec3d = signed_volume_2d(pt_a.xyz, pt_b.xyz, pt_c.xyz);
+
+    let pt_on_a: Vec3d = pt_a.pt_a * lambdas.x + pt_b.pt_a * lambdas.y + pt_c.pt_a * lambdas.z;
+    let pt_on_b: Vec3d = pt_a.pt_b * lambdas.x + pt_b.pt_b * lambdas.y + pt_c.pt_b * lambdas.z;
+    (pt_on_a, pt_on_b)
+}
+
+fn triangle_normal(triangle: &Triangle, points: &[SupportPoint]) -> Vec3d {
+    let a: Vec3d = points[triangle.a].xyz;
+    let b: Vec3d = points[triangle.b].xyz;
+    let c: Vec3d = points[triangle.c].xyz;
+    (b - a).cross(c - a).normalize()
+}
+
+/// Positive when the point is in front of the triangle
+fn signed_distance_to_triangle(triangle: &Triangle, point: &Vec3d, points: &[SupportPoint]) -> f64 {
+    let normal: Vec3d = triangle_normal(triangle, points);
+    normal.dot(*point - points[triangle.a].xyz)
+}
+
+fn closest_triangle(triangles: &[Triangle], points: &[SupportPoint]) -> Triangle {
+    *triangles
+        .iter()
+        .min_by(|a, b| {
+            let dist_a: f64 = f64::abs(signed_distance_to_triangle(a, &Vec3d::zero(), points));
+            let dist_b: f64 = f64::abs(signed_distance_to_triangle(b, &Vec3d::zero(), points));
+            dist_a.total_cmp(&dist_b)
+        })
+        .expect("the polytope always has faces")
+}
+
+/// Edges on the border of the removed faces, an edge shared by two removed faces is inside the hole
+fn dangling_edges(removed: &[Triangle]) -> Vec<(usize, usize)> {
+    let edges: Vec<(usize, usize)> = removed
+        .iter()
+        .flat_map(|triangle| [(triangle.a, triangle.b), (triangle.b, triangle.c), (triangle.c, triangle.a)])
+        .collect();
+
+    // Neighbouring faces share an edge in opposite directions
+    edges
+        .iter()
+        .filter(|(a, b)| !edges.contains(&(*b, *a)))
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::collision::intersect::closest_points;
+    use crate::physics::quat::quat::Quat;
+    use crate::physics::shape::{ShapeConstructor, ShapeT};
+
+    const TOLERANCE: f64 = 0.005;
+
+    fn new_body(shape: ShapeT, position: Vec3d, orientation: Quat) -> Body {
+        let mut body: Body = Body::new_shape(shape);
+        body.position = position;
+        body.orientation = orientation;
+        body
+    }
+
+    fn unit_box(position: Vec3d) -> Body {
+        new_body(ShapeT::new_box(1.0, 1.0, 1.0), position, Quat::zero())
+    }
+
+    fn assert_vec_near(actual: Vec3d, expected: Vec3d) {
+        assert!((actual - expected).get_magnitude() < TOLERANCE, "expected {:?}, got {:?}", expected, actual);
+    }
+
+    fn assert_near(actual: f64, expected: f64) {
+        assert!(f64::abs(actual - expected) < TOLERANCE, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn box_box_face_overlap() {
+        let body_a: Body = unit_box(Vec3d::zero());
+        let body_b: Body = unit_box(Vec3d::new(0.0, 0.8, 0.0));
+
+        let contact: Contact = epa(BodyHandle(0), &body_a, BodyHandle(1), &body_b).unwrap();
+        assert_vec_near(contact.normal, Vec3d::new(0.0, 1.0, 0.0));
+        assert_near(contact.separation_distance, -0.2);
+        assert_near(contact.pt_on_a_world_space.y, 0.5);
+        assert_near(contact.pt_on_b_world_space.y, 0.3);
+        assert_eq!(contact.body_a, BodyHandle(0));
+        assert_eq!(contact.body_b, BodyHandle(1));
+    }
+
+    #[test]
+    fn box_box_normal_points_from_a_to_b() {
+        let body_a: Body = unit_box(Vec3d::zero());
+        let body_b: Body = unit_box(Vec3d::new(-0.9, 0.0, 0.0));
+
+        let contact: Contact = epa(BodyHandle(0), &body_a, BodyHandle(1), &body_b).unwrap();
+        assert_vec_near(contact.normal, Vec3d::new(-1.0, 0.0, 0.0));
+        assert_near(contact.separation_distance, -0.1);
+    }
+
+    #[test]
+    fn box_box_rotated_edge() {
+        // B stands on one of its edges, which sinks 0.1 into the top face of A
+        let half_diagonal: f64 = f64::sqrt(2.0) * 0.5;
+        let orientation: Quat = Quat::from_axis_angle(Vec3d::new(0.0, 0.0, 1.0), std::f64::consts::FRAC_PI_4);
+        let body_a: Body = unit_box(Vec3d::zero());
+        let body_b: Body = new_body(ShapeT::new_box(1.0, 1.0, 1.0), Vec3d::new(0.0, 0.4 + half_diagonal, 0.0), orientation);
+
+        let contact: Contact = epa(BodyHandle(0), &body_a, BodyHandle(1), &body_b).unwrap();
+        assert_vec_near(contact.normal, Vec3d::new(0.0, 1.0, 0.0));
+        assert_near(contact.separation_distance, -0.1);
+        assert_near(contact.pt_on_b_world_space.x, 0.0);
+    }
+
+    #[test]
+    fn box_box_touching_faces() {
+        let body_a: Body = unit_box(Vec3d::zero());
+        let body_b: Body = unit_box(Vec3d::new(0.0, 1.0, 0.0));
+
+        let contact: Contact = epa(BodyHandle(0), &body_a, BodyHandle(1), &body_b).unwrap();
+        assert_vec_near(contact.normal, Vec3d::new(0.0, 1.0, 0.0));
+        assert_near(contact.separation_distance, 0.0);
+    }
+
+    #[test]
+    fn box_box_coincident() {
+        // The origin is in the middle of the Minkowski difference, any face is a valid way out
+        let body_a: Body = unit_box(Vec3d::zero());
+        let body_b: Body = unit_box(Vec3d::zero());
+
+        let contact: Contact = epa(BodyHandle(0), &body_a, BodyHandle(1), &body_b).unwrap();
+        assert_near(contact.separation_distance, -1.0);
+        assert_near(contact.normal.get_magnitude(), 1.0);
+        assert!((0..3).any(|i: usize| f64::abs(contact.normal[i]) > 1.0 - TOLERANCE));
+    }
+
+    #[test]
+    fn box_box_apart() {
+        let body_a: Body = unit_box(Vec3d::zero());
+        let body_b: Body = unit_box(Vec3d::new(0.0, 1.5, 0.0));
+
+        assert!(epa(BodyHandle(0), &body_a, BodyHandle(1), &body_b).is_none());
+    }
+
+    #[test]
+    fn box_sphere_face_overlap() {
+        let body_a: Body = unit_box(Vec3d::zero());
+        let body_b: Body = new_body(ShapeT::new_sphere(0.5), Vec3d::new(0.1, 0.9, 0.0), Quat::zero());
+
+        let contact: Contact = epa(BodyHandle(0), &body_a, BodyHandle(1), &body_b).unwrap();
+        assert_vec_near(contact.normal, Vec3d::new(0.0, 1.0, 0.0));
+        assert_near(contact.separation_distance, -0.1);
+        assert_vec_near(contact.pt_on_b_world_space, Vec3d::new(0.1, 0.4, 0.0));
+    }
+
+    #[test]
+    fn box_sphere_edge_matches_analytic() {
+        let body_a: Body = unit_box(Vec3d::zero());
+        let body_b: Body = new_body(ShapeT::new_sphere(0.5), Vec3d::new(0.7, 0.7, 0.0), Quat::zero());
+
+        let expected: Contact = closest_points(BodyHandle(0), &body_a, BodyHandle(1), &body_b).unwrap();
+        let contact: Contact = epa(BodyHandle(0), &body_a, BodyHandle(1), &body_b).unwrap();
+        assert_vec_near(contact.normal, Vec3d::new(1.0, 1.0, 0.0).normalize());
+        assert_vec_near(contact.normal, expected.normal);
+        assert_near(contact.separation_distance, expected.separation_distance);
+    }
+
+    #[test]
+    fn sphere_inside_box() {
+        // The GJK simplex around a point deep inside the box is small, EPA has to complete it first
+        let body_a: Body = new_body(ShapeT::new_sphere(0.25), Vec3d::new(0.0, 0.3, 0.0), Quat::zero());
+        let body_b: Body = unit_box(Vec3d::zero());
+
+        let contact: Contact = epa(BodyHandle(0), &body_a, BodyHandle(1), &body_b).unwrap();
+        assert_vec_near(contact.normal, Vec3d::new(0.0, -1.0, 0.0));
+        assert_near(contact.separation_distance, -0.45);
+    }
+}
@@ -0,0 +1,378 @@
+use crate::physics::body::Body;
+use crate::physics::mat::mat3::Mat3;
+use crate::physics::vec::vec2d::Vec2d;
+use crate::physics::vec::vec3d::Vec3d;
+use crate::physics::vec::vec4d::Vec4d;
+
+// Squared distance under which the origin counts as inside the simplex
+const ORIGIN_EPSILON_SQUARED: f64 = 0.0001 * 0.0001;
+// Squared distance under which two support points are the same
+const POINT_EPSILON_SQUARED: f64 = 1e-6 * 1e-6;
+
+/// A point of the Minkowski difference A - B, with the support points of A and B that made it
+#[derive(Debug, Clone, Copy)]
+pub struct SupportPoint {
+    pub xyz: Vec3d,
+    pub pt_a: Vec3d,
+    pub pt_b: Vec3d
+}
+
+impl PartialEq for SupportPoint {
+    fn eq(&self, other: &Self) -> bool {
+        (self.xyz - other.xyz).get_magnitude_squared() < POINT_EPSILON_SQUARED
+    }
+}
+
+/// A convex set GJK and EPA can run on, described by its furthest point in any direction
+pub trait ConvexSupport {
+    /// Furthest point along dir in world space, pushed out by bias
+    fn support(&self, dir: &Vec3d, bias: f64) -> Vec3d;
+
+    /// Any point inside the set, in world space
+    fn get_center(&self) -> Vec3d;
+}
+
+impl ConvexSupport for Body {
+    fn support(&self, dir: &Vec3d, bias: f64) -> Vec3d {
+        Body::support(self, dir, bias)
+    }
+
+    fn get_center(&self) -> Vec3d {
+        self.get_center_of_mass_world_space()
+    }
+}
+
+/// A single point, for measuring how far a point is from a shape
+pub struct PointSupport {
+    pub point: Vec3d
+}
+
+impl ConvexSupport for PointSupport {
+    fn support(&self, dir: &Vec3d, bias: f64) -> Vec3d {
+        self.point + dir.normalize() * bias
+    }
+
+    fn get_center(&self) -> Vec3d {
+        self.point
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GjkResult {
+    pub intersects: bool,
+    pub pt_on_a: Vec3d, // Closest points in world space, only meaningful when not intersecting
+    pub pt_on_b: Vec3d,
+    pub distance: f64
+}
+
+/// Support point of the Minkowski difference A - B in direction dir.
+/// Both bodies are pushed out by bias.
+pub fn support(body_a: &impl ConvexSupport, body_b: &impl ConvexSupport, dir: Vec3d, bias: f64) -> SupportPoint {
+    let dir: Vec3d = dir.normalize();
+    let pt_a: Vec3d = body_a.support(&dir, bias);
+    let pt_b: Vec3d = body_b.support(&-dir, bias);
+
+    SupportPoint {
+        xyz: pt_a - pt_b,
+        pt_a,
+        pt_b
+    }
+}
+
+/// Distance and closest points between two convex bodies
+pub fn gjk(body_a: &impl ConvexSupport, body_b: &impl ConvexSupport) -> GjkResult {
+    if gjk_intersection_simplex(body_a, body_b, 0.0).is_some() {
+        return GjkResult {
+            intersects: true,
+            pt_on_a: body_a.get_center(),
+            pt_on_b: body_b.get_center(),
+            distance: 0.0
+        };
+    }
+
+    let (pt_on_a, pt_on_b) = gjk_closest_points(body_a, body_b);
+    GjkResult {
+        intersects: false,
+        pt_on_a,
+        pt_on_b,
+        distance: (pt_on_b - pt_on_a).get_magnitude()
+    }
+}
+
+pub fn gjk_does_intersect(body_a: &impl ConvexSupport, body_b: &impl ConvexSupport) -> bool {
+    gjk_intersection_simplex(body_a, body_b, 0.0).is_some()
+}
+
+/// Runs GJK on the bodies pushed out by bias.
+/// When they intersect, returns the simplex of the Minkowski difference that contains the origin.
+pub fn gjk_intersection_simplex(body_a: &impl ConvexSupport, body_b: &impl ConvexSupport,
+                                bias: f64) -> Option<Vec<SupportPoint>> {
+    let mut simplex: Vec<SupportPoint> = vec![support(body_a, body_b, Vec3d::new(1.0, 1.0, 1.0), bias)];
+    let mut closest_dist: f64 = f64::MAX;
+    let mut new_dir: Vec3d = -simplex[0].xyz;
+
+    loop {
+        // Get the new point to check on
+        let new_pt: SupportPoint = support(body_a, body_b, new_dir, bias);
+
+        // If the new point is the same as a previous point, then we can't expand any further
+        if simplex.contains(&new_pt) {
+            return None;
+        }
+        simplex.push(new_pt);
+
+        // If this new point hasn't moved past the origin, then the origin cannot be in the set
+        if new_dir.dot(new_pt.xyz) < 0.0 {
+            return None;
+        }
+
+        let (lambdas, closest) = simplex_signed_volumes(&simplex);
+        if closest.get_magnitude_squared() < ORIGIN_EPSILON_SQUARED {
+            return Some(simplex);
+        }
+
+        // Check that the new projection of the origin onto the simplex is closer than the previous
+        let dist: f64 = closest.get_magnitude_squared();
+        if dist >= closest_dist {
+            return None;
+        }
+        closest_dist = dist;
+        new_dir = -closest;
+
+        // Only keep the points that support the new search direction
+        keep_valid_points(&mut simplex, &lambdas);
+
+        // A full tetrahedron only survives when the origin is inside it
+        if simplex.len() == 4 {
+            return Some(simplex);
+        }
+    }
+}
+
+/// Closest points between two bodies that don't intersect, in world space
+pub fn gjk_closest_points(body_a: &impl ConvexSupport, body_b: &impl ConvexSupport) -> (Vec3d, Vec3d) {
+    let mut simplex: Vec<SupportPoint> = vec![support(body_a, body_b, Vec3d::new(1.0, 1.0, 1.0), 0.0)];
+    let mut lambdas: Vec4d = Vec4d::new(1.0, 0.0, 0.0, 0.0);
+    let mut closest_dist: f64 = f64::MAX;
+    let mut new_dir: Vec3d = -simplex[0].xyz;
+
+    loop {
+        let new_pt: SupportPoint = support(body_a, body_b, new_dir, 0.0);
+        if simplex.contains(&new_pt) {
+            break;
+        }
+        simplex.push(new_pt);
+
+        let (new_lambdas, closest) = simplex_signed_volumes(&simplex);
+        let dist: f64 = closest.get_magnitude_squared();
+        if dist >= closest_dist {
+            // The new point doesn't get any closer, keep the last simplex
+            simplex.pop();
+            break;
+        }
+
+        lambdas = new_lambdas;
+        closest_dist = dist;
+        new_dir = -closest;
+        lambdas = keep_valid_points(&mut simplex, &lambdas);
+
+        if simplex.len() == 4 {
+            break;
+        }
+    }
+
+    let mut pt_on_a: Vec3d = Vec3d::zero();
+    let mut pt_on_b: Vec3d = Vec3d::zero();
+    for (i, point) in simplex.iter().enumerate() {
+        pt_on_a += point.pt_a * lambdas[i];
+        pt_on_b += point.pt_b * lambdas[i];
+    }
+
+    (pt_on_a, pt_on_b)
+}
+
+/// Barycentric coordinates of the point of the simplex closest to the origin, and that point
+fn simplex_signed_volumes(simplex: &[SupportPoint]) -> (Vec4d, Vec3d) {
+    let lambdas: Vec4d = match simplex.len() {
+        1 => Vec4d::new(1.0, 0.0, 0.0, 0.0),
+        2 => {
+            let l: Vec2d = signed_volume_1d(simplex[0].xyz, simplex[1].xyz);
+            Vec4d::new(l.x, l.y, 0.0, 0.0)
+        },
+        3 => {
+            let l: Vec3d = signed_volume_2d(simplex[0].xyz, simplex[1].xyz, simplex[2].xyz);
+            Vec4d::new(l.x, l.y, l.z, 0.0)
+        },
+        _ => signed_volume_3d(simplex[0].xyz, simplex[1].xyz, simplex[2].xyz, simplex[3].xyz)
+    };
+
+    let mut closest: Vec3d = Vec3d::zero();
+    for (i, point) in simplex.iter().enumerate() {
+        closest += point.xyz * lambdas[i];
+    }
+
+    (lambdas, closest)
+}
+
+/// Drops the points with a zero barycentric coordinate, returns the lambdas of the points left
+fn keep_valid_points(simplex: &mut Vec<SupportPoint>, lambdas: &Vec4d) -> Vec4d {
+    let mut valid_lambdas: Vec4d = Vec4d::zero();
+    let mut valid: Vec<SupportPoint> = Vec::with_capacity(4);
+
+    for (i, point) in simplex.iter().enumerate() {
+        if lambdas[i] != 0.0 {
+            valid_lambdas[valid.len()] = lambdas[i];
+            valid.push(*point);
+        }
+    }
+
+    *simplex = valid;
+    valid_lambdas
+}
+
+fn compare_signs(a: f64, b: f64) -> bool {
+    (a > 0.0 && b > 0.0) || (a < 0.0 && b < 0.0)
+}
+
+/// Barycentric coordinates of the point of the segment closest to the origin
+fn signed_volume_1d(s1: Vec3d, s2: Vec3d) -> Vec2d {
+    // Projection of the origin onto the line
+    let ab: Vec3d = s2 - s1;
+    let ap: Vec3d = -s1;
+    let p0: Vec3d = s1 + ab * (ab.dot(ap) / ab.get_magnitude_squared());
+
+    // Choose the axis with the greatest difference
+    let mut idx: usize = 0;
+    let mut mu_max: f64 = 0.0;
+    for i in 0..3 {
+        let mu: f64 = s2[i] - s1[i];
+        if mu * mu > mu_max * mu_max {
+            mu_max = mu;
+            idx = i;
+        }
+    }
+
+    // Project the simplex points and projected origin onto the axis with greatest length
+    let (a, b, p) = (s1[idx], s2[idx], p0[idx]);
+
+    // Get the signed distance from a to p and from p to b
+    let c1: f64 = p - a;
+    let c2: f64 = b - p;
+
+    // If p is between [a, b]
+    if (p > a && p < b) || (p > b && p < a) {
+        return Vec2d::new(c2 / mu_max, c1 / mu_max);
+    }
+
+    // If p is on the far side of a
+    if (a <= b && p <= a) || (a >= b && p >= a) {
+        return Vec2d::new(1.0, 0.0);
+    }
+
+    // p must be on the far side of b
+    Vec2d::new(0.0, 1.0)
+}
+
+/// Barycentric coordinates of the point of the triangle closest to the origin
+pub fn signed_volume_2d(s1: Vec3d, s2: Vec3d, s3: Vec3d) -> Vec3d {
+    let normal: Vec3d = (s2 - s1).cross(s3 - s1);
+    let p0: Vec3d = normal * (s1.dot(normal) / normal.get_magnitude_squared());
+
+    // Find the axis with the greatest projected area
+    let mut idx: usize = 0;
+    let mut area_max: f64 = 0.0;
+    for i in 0..3 {
+        let (j, k) = ((i + 1) % 3, (i + 2) % 3);
+
+        let a: Vec2d = Vec2d::new(s1[j], s1[k]);
+        let b: Vec2d = Vec2d::new(s2[j], s2[k]);
+        let c: Vec2d = Vec2d::new(s3[j], s3[k]);
+        let ab: Vec2d = b - a;
+        let ac: Vec2d = c - a;
+
+        let area: f64 = ab.x * ac.y - ab.y * ac.x;
+        if area * area > area_max * area_max {
+            idx = i;
+            area_max = area;
+        }
+    }
+
+    // Project onto the appropriate axis
+    let (x, y) = ((idx + 1) % 3, (idx + 2) % 3);
+    let s: [Vec2d; 3] = [
+        Vec2d::new(s1[x], s1[y]),
+        Vec2d::new(s2[x], s2[y]),
+        Vec2d::new(s3[x], s3[y])
+    ];
+    let p: Vec2d = Vec2d::new(p0[x], p0[y]);
+
+    // Get the sub-areas of the triangles formed from the projected origin and the edges
+    let mut areas: Vec3d = Vec3d::zero();
+    for i in 0..3 {
+        let (j, k) = ((i + 1) % 3, (i + 2) % 3);
+        let ab: Vec2d = s[j] - p;
+        let ac: Vec2d = s[k] - p;
+        areas[i] = ab.x * ac.y - ab.y * ac.x;
+    }
+
+    // If the projected origin is inside the triangle, then return the barycentric points
+    if compare_signs(area_max, areas[0]) && compare_signs(area_max, areas[1]) && compare_signs(area_max, areas[2]) {
+        return areas / area_max;
+    }
+
+    // If we make it here, then we need to project onto the edges and determine the closest point
+    let points: [Vec3d; 3] = [s1, s2, s3];
+    let mut dist: f64 = f64::MAX;
+    let mut lambdas: Vec3d = Vec3d::new(1.0, 0.0, 0.0);
+    for i in 0..3 {
+        let (k, l) = ((i + 1) % 3, (i + 2) % 3);
+
+        let lambda_edge: Vec2d = signed_volume_1d(points[k], points[l]);
+        let point: Vec3d = points[k] * lambda_edge.x + points[l] * lambda_edge.y;
+        if point.get_magnitude_squared() < dist {
+            dist = point.get_magnitude_squared();
+            lambdas = Vec3d::zero();
+            lambdas[k] = lambda_edge.x;
+            lambdas[l] = lambda_edge.y;
+        }
+    }
+
+    lambdas
+}
+
+/// Barycentric coordinates of the point of the tetrahedron closest to the origin
+fn signed_volume_3d(s1: Vec3d, s2: Vec3d, s3: Vec3d, s4: Vec3d) -> Vec4d {
+    // Cofactors of the last row of the matrix with the points as columns and a row of ones
+    let c4: Vec4d = Vec4d::new(
+        -Mat3::new(s2, s3, s4).determinant(),
+        Mat3::new(s1, s3, s4).determinant(),
+        -Mat3::new(s1, s2, s4).determinant(),
+        Mat3::new(s1, s2, s3).determinant()
+    );
+    let det_m: f64 = c4.x + c4.y + c4.z + c4.w;
+
+    // If the barycentric coordinates put the origin inside the simplex, then return them
+    if (0..4).all(|i: usize| compare_signs(det_m, c4[i])) {
+        return c4 / det_m;
+    }
+
+    // If we get here, then we need to project the origin onto the faces and determine the closest one
+    let points: [Vec3d; 4] = [s1, s2, s3, s4];
+    let mut lambdas: Vec4d = Vec4d::zero();
+    let mut dist: f64 = f64::MAX;
+    for i in 0..4 {
+        let (j, k) = ((i + 1) % 4, (i + 2) % 4);
+
+        let lambdas_face: Vec3d = signed_volume_2d(points[i], points[j], points[k]);
+        let point: Vec3d = points[i] * lambdas_face.x + points[j] * lambdas_face.y + points[k] * lambdas_face.z;
+        if point.get_magnitude_squared() < dist {
+            dist = point.get_magnitude_squared();
+            lambdas = Vec4d::zero();
+            lambdas[i] = lambdas_face.x;
+            lambdas[j] = lambdas_face.y;
+            lambdas[k] = lambdas_face.z;
+        }
+    }
+
+    lambdas
+}
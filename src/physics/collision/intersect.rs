@@ -0,0 +1,231 @@
+use crate::physics::body::Body;
+use crate::physics::collision::contact::Contact;
+use crate::physics::collision::epa::epa;
+use crate::physics::collision::gjk::{
+    gjk,
+    GjkResult
+};
+use crate::physics::collision::mesh::{
+    mesh_closest_points,
+    mesh_intersect
+};
+use crate::physics::collision::plane::{
+    plane_closest_points,
+    plane_intersect
+};
+use crate::physics::collision::sat::box_box;
+use crate::physics::scene::BodyHandle;
+use crate::physics::shape::ShapeT;
+use crate::physics::vec::vec3d::Vec3d;
+
+/// Narrowphase entry point, returns the contact manifold between two bodies.
+/// The manifold is empty when the bodies don't touch. Its points share the same normal,
+/// except for compound shapes, whose manifold gathers the manifolds of their children,
+/// and triangle meshes and heightfields, whose manifold has a point per touched triangle.
+pub fn intersect(handle_a: BodyHandle, body_a: &Body, handle_b: BodyHandle, body_b: &Body) -> Vec<Contact> {
+    match (&body_a.shape, &body_b.shape) {
+        (ShapeT::CompoundShape { children, .. }, _) =>
+            children
+                .iter()
+                .flat_map(|child| intersect(handle_a, &body_a.child_body(child), handle_b, body_b))
+                .map(|contact| rebase_contact(contact, body_a, body_b))
+                .collect(),
+
+        (_, ShapeT::CompoundShape { .. }) =>
+            intersect(handle_b, body_b, handle_a, body_a)
+                .into_iter()
+                .map(|contact| contact.swapped())
+                .collect(),
+
+        // Triangle meshes, heightfields and planes are static, two of them never need to collide
+        (shape_a, shape_b) if shape_a.is_static_only() && shape_b.is_static_only() => Vec::new(),
+
+        (ShapeT::TriangleMeshShape { mesh }, _) =>
+            mesh_intersect(handle_a, body_a, mesh.as_ref(), handle_b, body_b),
+
+        (ShapeT::HeightfieldShape { field }, _) =>
+            mesh_intersect(handle_a, body_a, field.as_ref(), handle_b, body_b),
+
+        (ShapeT::PlaneShape { normal, offset }, _) =>
+            plane_intersect(handle_a, body_a, normal, *offset, handle_b, body_b),
+
+        (_, shape_b) if shape_b.is_static_only() =>
+            intersect(handle_b, body_b, handle_a, body_a)
+                .into_iter()
+                .map(|contact| contact.swapped())
+                .collect(),
+
+        (ShapeT::BoxShape { width: width_a, length: length_a, height: height_a, .. },
+         ShapeT::BoxShape { width: width_b, length: length_b, height: height_b, .. }) =>
+            box_box(handle_a, body_a, half_extents(*width_a, *length_a, *height_a),
+                    handle_b, body_b, half_extents(*width_b, *length_b, *height_b)),
+
+        _ => closest_points(handle_a, body_a, handle_b, body_b)
+                .filter(|contact| contact.separation_distance <= 0.0)
+                .into_iter()
+                .collect()
+    }
+}
+
+/// Closest points between two bodies, also when they are apart (positive separation distance).
+/// Returns None when the pair overlaps and its shapes have no penetration query,
+/// or when a triangle mesh or heightfield has no triangle near the other body.
+pub fn closest_points(handle_a: BodyHandle, body_a: &Body, handle_b: BodyHandle, body_b: &Body) -> Option<Contact> {
+    match (&body_a.shape, &body_b.shape) {
+        // The closest child is the closest part of the compound
+        (ShapeT::CompoundShape { children, .. }, _) =>
+            children
+                .iter()
+                .filter_map(|child| closest_points(handle_a, &body_a.child_body(child), handle_b, body_b))
+                .map(|contact| rebase_contact(contact, body_a, body_b))
+                .min_by(|a, b| a.separation_distance.total_cmp(&b.separation_distance)),
+
+        (_, ShapeT::CompoundShape { .. }) =>
+            closest_points(handle_b, body_b, handle_a, body_a).map(|contact| contact.swapped()),
+
+        (shape_a, shape_b) if shape_a.is_static_only() && shape_b.is_static_only() => None,
+
+        (ShapeT::TriangleMeshShape { mesh }, _) =>
+            mesh_closest_points(handle_a, body_a, mesh.as_ref(), handle_b, body_b),
+
+        (ShapeT::HeightfieldShape { field }, _) =>
+            mesh_closest_points(handle_a, body_a, field.as_ref(), handle_b, body_b),
+
+        (ShapeT::PlaneShape { normal, offset }, _) =>
+            Some(plane_closest_points(handle_a, body_a, normal, *offset, handle_b, body_b)),
+
+        (_, shape_b) if shape_b.is_static_only() =>
+            closest_points(handle_b, body_b, handle_a, body_a).map(|contact| contact.swapped()),
+
+        (ShapeT::SphereShape { radius: radius_a, .. }, ShapeT::SphereShape { radius: radius_b, .. }) =>
+            Some(sphere_sphere(handle_a, body_a, *radius_a, handle_b, body_b, *radius_b)),
+
+        (ShapeT::SphereShape { radius, .. }, ShapeT::BoxShape { width, length, height, .. }) =>
+            Some(sphere_box(handle_a, body_a, *radius, handle_b, body_b, half_extents(*width, *length, *height))),
+
+        (ShapeT::BoxShape { .. }, ShapeT::SphereShape { .. }) =>
+            closest_points(handle_b, body_b, handle_a, body_a).map(|contact| contact.swapped()),
+
+        // Everything else is convex, GJK and EPA only need the support functions
+        _ => convex_closest_points(handle_a, body_a, handle_b, body_b)
+    }
+}
+
+/// Closest points between any two convex bodies, using GJK while they are apart and EPA once they overlap.
+/// Returns None when the overlap is too degenerate for EPA to measure.
+pub fn convex_closest_points(handle_a: BodyHandle, body_a: &Body, handle_b: BodyHandle, body_b: &Body) -> Option<Contact> {
+    let result: GjkResult = gjk(body_a, body_b);
+    if result.intersects {
+        return epa(handle_a, body_a, handle_b, body_b);
+    }
+
+    let normal: Vec3d = (result.pt_on_b - result.pt_on_a).normalize();
+    Some(Contact {
+        pt_on_a_world_space: result.pt_on_a,
+        pt_on_b_world_space: result.pt_on_b,
+        pt_on_a_local_space: body_a.world_space_to_body_space(&result.pt_on_a),
+        pt_on_b_local_space: body_b.world_space_to_body_space(&result.pt_on_b),
+        normal,
+        separation_distance: result.distance,
+        time_of_impact: 0.0,
+        from_ccd: false,
+        body_a: handle_a,
+        body_b: handle_b
+    })
+}
+
+/// Contacts found against a child of a compound are relative to the child's stand-in body,
+/// the local points have to be relative to the real bodies
+fn rebase_contact(mut contact: Contact, body_a: &Body, body_b: &Body) -> Contact {
+    contact.pt_on_a_local_space = body_a.world_space_to_body_space(&contact.pt_on_a_world_space);
+    contact.pt_on_b_local_space = body_b.world_space_to_body_space(&contact.pt_on_b_world_space);
+    contact
+}
+
+fn half_extents(width: f64, length: f64, height: f64) -> Vec3d {
+    Vec3d::new(width, length, height) * 0.5
+}
+
+fn sphere_sphere(handle_a: BodyHandle, body_a: &Body, radius_a: f64,
+                 handle_b: BodyHandle, body_b: &Body, radius_b: f64) -> Contact {
+    let ab: Vec3d = body_b.position - body_a.position;
+    let radius_ab: f64 = radius_a + radius_b;
+    let distance: f64 = ab.get_magnitude();
+
+    // Concentric spheres have no preferred direction, push them apart along an arbitrary axis
+    let normal: Vec3d = if distance > 0.0 {
+        ab / distance
+    } else {
+        Vec3d::new(0.0, 1.0, 0.0)
+    };
+
+    let pt_on_a: Vec3d = body_a.position + normal * radius_a;
+    let pt_on_b: Vec3d = body_b.position - normal * radius_b;
+
+    Contact {
+        pt_on_a_world_space: pt_on_a,
+        pt_on_b_world_space: pt_on_b,
+        pt_on_a_local_space: body_a.world_space_to_body_space(&pt_on_a),
+        pt_on_b_local_space: body_b.world_space_to_body_space(&pt_on_b),
+        normal,
+        separation_distance: distance - radius_ab,
+        time_of_impact: 0.0,
+        from_ccd: false,
+        body_a: handle_a,
+        body_b: handle_b
+    }
+}
+
+fn sphere_box(handle_a: BodyHandle, body_a: &Body, radius: f64,
+              handle_b: BodyHandle, body_b: &Body, half_extents: Vec3d) -> Contact {
+    // Work in the box's space, where it is axis aligned around the origin
+    let center: Vec3d = body_b.world_space_to_body_space(&body_a.position);
+
+    let mut closest: Vec3d = center;
+    for i in 0..3 {
+        closest[i] = center[i].clamp(-half_extents[i], half_extents[i]);
+    }
+
+    // Normal in the box's space, pointing from the box towards the sphere
+    let (pt_on_box, normal_box_space, separation_distance): (Vec3d, Vec3d, f64) = if closest != center {
+        let delta: Vec3d = center - closest;
+        let distance: f64 = delta.get_magnitude();
+        (closest, delta / distance, distance - radius)
+    } else {
+        // The center is inside the box, push it out through the nearest face
+        let mut axis: usize = 0;
+        let mut face_distance: f64 = f64::MAX;
+        for i in 0..3 {
+            let distance: f64 = half_extents[i] - f64::abs(center[i]);
+            if distance < face_distance {
+                face_distance = distance;
+                axis = i;
+            }
+        }
+
+        let sign: f64 = if center[axis] < 0.0 { -1.0 } else { 1.0 };
+        let mut normal: Vec3d = Vec3d::zero();
+        normal[axis] = sign;
+        let mut pt_on_box: Vec3d = center;
+        pt_on_box[axis] = sign * half_extents[axis];
+        (pt_on_box, normal, -(face_distance + radius))
+    };
+
+    // The contact normal points from the sphere (A) towards the box (B)
+    let normal: Vec3d = -body_b.orientation.rotate_point(normal_box_space);
+    let pt_on_a: Vec3d = body_a.position + normal * radius;
+    let pt_on_b: Vec3d = body_b.body_space_to_world_space(&pt_on_box);
+
+    Contact {
+        pt_on_a_world_space: pt_on_a,
+        pt_on_b_world_space: pt_on_b,
+        pt_on_a_local_space: body_a.world_space_to_body_space(&pt_on_a),
+        pt_on_b_local_space: pt_on_box,
+        normal,
+        separation_distance,
+        time_of_impact: 0.0,
+        from_ccd: false,
+        body_a: handle_a,
+        body_b: handle_b
+    }
+}
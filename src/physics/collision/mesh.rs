@@ -0,0 +1,304 @@
+use crate::physics::body::Body;
+use crate::physics::bounds::Aabb;
+use crate::physics::collision::contact::Contact;
+use crate::physics::collision::epa::{
+    epa_penetration,
+    EpaResult
+};
+use crate::physics::collision::gjk::{
+    gjk,
+    ConvexSupport,
+    GjkResult
+};
+use crate::physics::quat::quat::Quat;
+use crate::physics::scene::BodyHandle;
+use crate::physics::shape::{
+    Shape,
+    ShapeT
+};
+use crate::physics::vec::vec3d::Vec3d;
+
+// Barycentric coordinates below this put a point on an edge or a corner of its triangle
+const FEATURE_EPSILON: f64 = 1e-4;
+// Contact normals closer than this to the face normal (cosine of the angle) already come from the face
+const FACE_NORMAL_COSINE: f64 = 0.9999;
+
+/// Static shapes made of triangles, in their body's space
+pub trait TriangleCollider {
+    /// Triangles whose bounds may overlap the given bounds
+    fn query_bounds(&self, bounds: &Aabb) -> Vec<usize>;
+    fn get_triangle_points(&self, triangle: usize) -> [Vec3d; 3];
+    /// Whether edge `edge` of the triangle, 0 for ab, 1 for bc and 2 for ca, can push bodies
+    fn is_edge_active(&self, triangle: usize, edge: usize) -> bool;
+}
+
+/// One triangle of a mesh, placed in world space
+struct WorldTriangle {
+    points: [Vec3d; 3]
+}
+
+impl ConvexSupport for WorldTriangle {
+    fn support(&self, dir: &Vec3d, bias: f64) -> Vec3d {
+        let point: Vec3d = *self.points
+            .iter()
+            .max_by(|a, b| a.dot(*dir).total_cmp(&b.dot(*dir)))
+            .expect("a triangle has points");
+        point + dir.normalize() * bias
+    }
+
+    fn get_center(&self) -> Vec3d {
+        (self.points[0] + self.points[1] + self.points[2]) / 3.0
+    }
+}
+
+/// Contact manifold between a triangle mesh or heightfield (A) and another body (B), one point per touched triangle
+pub fn mesh_intersect(handle_a: BodyHandle, body_a: &Body, mesh: &impl TriangleCollider,
+                      handle_b: BodyHandle, body_b: &Body) -> Vec<Contact> {
+    let bounds: Aabb = body_b.shape.get_bounds(&body_b.position, &body_b.orientation);
+    mesh_candidates(body_a, mesh, &bounds)
+        .into_iter()
+        .filter_map(|triangle| triangle_closest_points(handle_a, body_a, mesh, triangle, handle_b, body_b))
+        .filter(|contact| contact.separation_distance <= 0.0)
+        .collect()
+}
+
+/// Closest points between a triangle mesh or heightfield (A) and another body (B), among the triangles around B.
+/// Returns None when no triangle is near B.
+pub fn mesh_closest_points(handle_a: BodyHandle, body_a: &Body, mesh: &impl TriangleCollider,
+                           handle_b: BodyHandle, body_b: &Body) -> Option<Contact> {
+    let bounds: Aabb = body_b.shape.get_bounds(&body_b.position, &body_b.orientation);
+    mesh_candidates(body_a, mesh, &bounds)
+        .into_iter()
+        .filter_map(|triangle| triangle_closest_points(handle_a, body_a, mesh, triangle, handle_b, body_b))
+        .min_by(|a, b| a.separation_distance.total_cmp(&b.separation_distance))
+}
+
+/// Triangles of the mesh whose bounds overlap bounds given in world space
+pub fn mesh_candidates(body_a: &Body, mesh: &impl TriangleCollider, bounds: &Aabb) -> Vec<usize> {
+    // The BVH is in the mesh's space, bound the corners of the world bounds there
+    let inverse_orient: Quat = body_a.orientation.inverse();
+    let mut local_bounds: Aabb = Aabb::empty();
+    for corner in bounds.corners() {
+        local_bounds.expand_point(&inverse_orient.rotate_point(corner - body_a.position));
+    }
+    mesh.query_bounds(&local_bounds)
+}
+
+/// Closest points between one triangle of a mesh (A) and another body (B).
+/// Contacts on edges between coplanar faces, or inside concave folds, take the face normal instead,
+/// so bodies sliding across the mesh don't get pushed back by its seams.
+pub fn triangle_closest_points(handle_a: BodyHandle, body_a: &Body, mesh: &impl TriangleCollider, triangle: usize,
+                               handle_b: BodyHandle, body_b: &Body) -> Option<Contact> {
+    // Compounds aren't convex, the closest child is the closest part
+    if let ShapeT::CompoundShape { children, .. } = &body_b.shape {
+        return children
+            .iter()
+            .filter_map(|child| triangle_closest_points(handle_a, body_a, mesh, triangle, handle_b, &body_b.child_body(child)))
+            .map(|mut contact| {
+                contact.pt_on_b_local_space = body_b.world_space_to_body_space(&contact.pt_on_b_world_space);
+                contact
+            })
+            .min_by(|a, b| a.separation_distance.total_cmp(&b.separation_distance));
+    }
+
+    let points: [Vec3d; 3] = mesh
+        .get_triangle_points(triangle)
+        .map(|point| body_a.position + body_a.orientation.rotate_point(point));
+    let world_triangle: WorldTriangle = WorldTriangle { points };
+
+    // Triangles are two sided, the face normal points to whichever side B is on
+    let mut face_normal: Vec3d = (points[1] - points[0]).cross(points[2] - points[0]).normalize();
+    if face_normal.dot(body_b.get_center_of_mass_world_space() - points[0]) < 0.0 {
+        face_normal = -face_normal;
+    }
+
+    let (mut pt_on_a, mut pt_on_b, mut normal, mut separation_distance): (Vec3d, Vec3d, Vec3d, f64) =
+        match &body_b.shape {
+            ShapeT::SphereShape { radius, .. } => {
+                let center: Vec3d = body_b.get_center_of_mass_world_space();
+                let closest: Vec3d = closest_point_on_triangle(&points, &center);
+                let delta: Vec3d = center - closest;
+                let distance: f64 = delta.get_magnitude();
+
+                // A center on the triangle has no direction to it, push it out along the face
+                let normal: Vec3d = if distance > 0.0 {
+                    delta / distance
+                } else {
+                    face_normal
+                };
+                (closest, center - normal * *radius, normal, distance - radius)
+            },
+            _ => {
+                let result: GjkResult = gjk(&world_triangle, body_b);
+                if result.intersects {
+                    let result: EpaResult = epa_penetration(&world_triangle, body_b)?;
+                    (result.pt_on_a, result.pt_on_b, result.normal, -result.depth)
+                } else {
+                    let normal: Vec3d = (result.pt_on_b - result.pt_on_a).normalize();
+                    (result.pt_on_a, result.pt_on_b, normal, result.distance)
+                }
+            }
+        };
+
+    // Bodies touching an internal edge or vertex are really touching the surface the face is part of,
+    // measure the contact along the face normal
+    let is_internal: bool = !is_feature_active(mesh, triangle, &points, &pt_on_a);
+    if separation_distance <= 0.0 && is_internal && normal.dot(face_normal) < FACE_NORMAL_COSINE {
+        pt_on_b = body_b.support(&-face_normal, 0.0);
+        separation_distance = face_normal.dot(pt_on_b - points[0]);
+        pt_on_a = pt_on_b - face_normal * separation_distance;
+        normal = face_normal;
+    }
+
+    // The normal points from A to B
+    Some(Contact {
+        pt_on_a_world_space: pt_on_a,
+        pt_on_b_world_space: pt_on_b,
+        pt_on_a_local_space: body_a.world_space_to_body_space(&pt_on_a),
+        pt_on_b_local_space: body_b.world_space_to_body_space(&pt_on_b),
+        normal,
+        separation_distance,
+        time_of_impact: 0.0,
+        from_ccd: false,
+        body_a: handle_a,
+        body_b: handle_b
+    })
+}
+
+/// Distance along the ray where it crosses the triangle, from either side.
+/// Returns None when the ray misses it or runs parallel to it.
+pub fn ray_triangle(start: &Vec3d, dir: &Vec3d, points: &[Vec3d; 3]) -> Option<f64> {
+    // Moller-Trumbore, solve start + dir * t = a + ab * u + ac * v
+    let ab: Vec3d = points[1] - points[0];
+    let ac: Vec3d = points[2] - points[0];
+    let p: Vec3d = dir.cross(ac);
+    let determinant: f64 = ab.dot(p);
+    if f64::abs(determinant) < f64::EPSILON {
+        return None;
+    }
+
+    let inv_determinant: f64 = 1.0 / determinant;
+    let s: Vec3d = *start - points[0];
+    let u: f64 = s.dot(p) * inv_determinant;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q: Vec3d = s.cross(ab);
+    let v: f64 = dir.dot(q) * inv_determinant;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t: f64 = ac.dot(q) * inv_determinant;
+    if t < 0.0 {
+        return None;
+    }
+    Some(t)
+}
+
+/// Whether the feature of the triangle the point is on can push bodies.
+/// The inside of the face always can, a vertex can when one of its edges can.
+fn is_feature_active(mesh: &impl TriangleCollider, triangle: usize, points: &[Vec3d; 3], point: &Vec3d) -> bool {
+    let [u, v, w] = barycentric(points, point);
+    let on_edge = |coordinate: f64| coordinate < FEATURE_EPSILON;
+
+    // Edges are 0 for ab, 1 for bc and 2 for ca, each one is opposite the vertex whose coordinate is zero
+    let mut edges: Vec<usize> = Vec::new();
+    if on_edge(w) {
+        edges.push(0);
+    }
+    if on_edge(u) {
+        edges.push(1);
+    }
+    if on_edge(v) {
+        edges.push(2);
+    }
+
+    match edges.len() {
+        0 => true,
+        1 => mesh.is_edge_active(triangle, edges[0]),
+        // On a vertex, the only coordinate left is the vertex's own
+        _ => {
+            let vertex_edges: [usize; 2] = if !on_edge(u) {
+                [0, 2] // a
+            } else if !on_edge(v) {
+                [0, 1] // b
+            } else {
+                [1, 2] // c
+            };
+            vertex_edges.iter().any(|edge| mesh.is_edge_active(triangle, *edge))
+        }
+    }
+}
+
+/// Barycentric coordinates of a point on the triangle's plane, the weights of a, b and c
+fn barycentric(points: &[Vec3d; 3], point: &Vec3d) -> [f64; 3] {
+    let ab: Vec3d = points[1] - points[0];
+    let ac: Vec3d = points[2] - points[0];
+    let ap: Vec3d = *point - points[0];
+
+    let d00: f64 = ab.dot(ab);
+    let d01: f64 = ab.dot(ac);
+    let d11: f64 = ac.dot(ac);
+    let d20: f64 = ap.dot(ab);
+    let d21: f64 = ap.dot(ac);
+    let denominator: f64 = d00 * d11 - d01 * d01;
+
+    let v: f64 = (d11 * d20 - d01 * d21) / denominator;
+    let w: f64 = (d00 * d21 - d01 * d20) / denominator;
+    [1.0 - v - w, v, w]
+}
+
+/// Point of the triangle closest to the given point, by finding which of its features is closest
+pub fn closest_point_on_triangle(points: &[Vec3d; 3], point: &Vec3d) -> Vec3d {
+    let [a, b, c] = *points;
+    let ab: Vec3d = b - a;
+    let ac: Vec3d = c - a;
+
+    // Vertex a
+    let ap: Vec3d = *point - a;
+    let d1: f64 = ab.dot(ap);
+    let d2: f64 = ac.dot(ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+
+    // Vertex b
+    let bp: Vec3d = *point - b;
+    let d3: f64 = ab.dot(bp);
+    let d4: f64 = ac.dot(bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+
+    // Edge ab
+    let vc: f64 = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+
+    // Vertex c
+    let cp: Vec3d = *point - c;
+    let d5: f64 = ab.dot(cp);
+    let d6: f64 = ac.dot(cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+
+    // Edge ac
+    let vb: f64 = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+
+    // Edge bc
+    let va: f64 = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    // Inside the face
+    let denominator: f64 = 1.0 / (va + vb + vc);
+    a + ab * (vb * denominator) + ac * (vc * denominator)
+}
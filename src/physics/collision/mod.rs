@@ -0,0 +1,10 @@
+pub mod contact;
+pub mod intersect;
+pub mod sat;
+pub mod toi;
+pub mod gjk;
+pub mod epa;
+pub mod mesh;
+pub mod plane;
+pub mod raycast;
+pub mod query;
\ No newline at end of file
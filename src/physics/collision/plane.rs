@@ -0,0 +1,92 @@
+use crate::physics::body::Body;
+use crate::physics::collision::contact::Contact;
+use crate::physics::scene::BodyHandle;
+use crate::physics::shape::{
+    box_corners,
+    ShapeT
+};
+use crate::physics::vec::vec3d::Vec3d;
+
+/// Contact manifold between a plane (A) and another body (B).
+/// Boxes and hulls get a point for every corner behind the plane so they can rest on a face,
+/// capsules and cylinders get one for each end, other shapes get their deepest point.
+pub fn plane_intersect(handle_a: BodyHandle, body_a: &Body, normal: &Vec3d, offset: f64,
+                       handle_b: BodyHandle, body_b: &Body) -> Vec<Contact> {
+    let (world_normal, plane_point) = world_plane(body_a, normal, offset);
+
+    let points: Vec<Vec3d> = match &body_b.shape {
+        ShapeT::BoxShape { width, length, height, .. } =>
+            box_corners(*width, *length, *height)
+                .iter()
+                .map(|corner| body_b.position + body_b.orientation.rotate_point(*corner))
+                .collect(),
+
+        ShapeT::ConvexShape { points, .. } =>
+            points
+                .iter()
+                .map(|point| body_b.position + body_b.orientation.rotate_point(*point))
+                .collect(),
+
+        ShapeT::CapsuleShape { radius, half_height, .. } =>
+            [*half_height, -*half_height]
+                .iter()
+                .map(|y| {
+                    let end: Vec3d = body_b.get_center_of_mass_world_space() + body_b.orientation.rotate_point(Vec3d::new(0.0, *y, 0.0));
+                    end - world_normal * *radius
+                })
+                .collect(),
+
+        // The deepest point of each cap's rim, a cylinder standing on a cap gets the cap's center
+        ShapeT::CylinderShape { radius, half_height, .. } => {
+            let axis: Vec3d = body_b.orientation.rotate_point(Vec3d::new(0.0, 1.0, 0.0));
+            let down: Vec3d = (-world_normal + axis * world_normal.dot(axis)).normalize();
+            [*half_height, -*half_height]
+                .iter()
+                .map(|y| body_b.get_center_of_mass_world_space() + axis * *y + down * *radius)
+                .collect()
+        },
+
+        _ => vec![body_b.support(&-world_normal, 0.0)]
+    };
+
+    points
+        .into_iter()
+        .map(|point| plane_contact(handle_a, body_a, &world_normal, &plane_point, handle_b, body_b, point))
+        .filter(|contact| contact.separation_distance <= 0.0)
+        .collect()
+}
+
+/// Closest points between a plane (A) and another body (B), the deepest point of B and its projection on the plane
+pub fn plane_closest_points(handle_a: BodyHandle, body_a: &Body, normal: &Vec3d, offset: f64,
+                            handle_b: BodyHandle, body_b: &Body) -> Contact {
+    let (world_normal, plane_point) = world_plane(body_a, normal, offset);
+    let deepest: Vec3d = body_b.support(&-world_normal, 0.0);
+    plane_contact(handle_a, body_a, &world_normal, &plane_point, handle_b, body_b, deepest)
+}
+
+/// Normal of the plane and a point on it, in world space
+fn world_plane(body: &Body, normal: &Vec3d, offset: f64) -> (Vec3d, Vec3d) {
+    let world_normal: Vec3d = body.orientation.rotate_point(*normal);
+    let plane_point: Vec3d = body.position + world_normal * offset;
+    (world_normal, plane_point)
+}
+
+/// Contact between the plane and a point of B, the normal is the plane's
+fn plane_contact(handle_a: BodyHandle, body_a: &Body, world_normal: &Vec3d, plane_point: &Vec3d,
+                 handle_b: BodyHandle, body_b: &Body, pt_on_b: Vec3d) -> Contact {
+    let separation_distance: f64 = world_normal.dot(pt_on_b - *plane_point);
+    let pt_on_a: Vec3d = pt_on_b - *world_normal * separation_distance;
+
+    Contact {
+        pt_on_a_world_space: pt_on_a,
+        pt_on_b_world_space: pt_on_b,
+        pt_on_a_local_space: body_a.world_space_to_body_space(&pt_on_a),
+        pt_on_b_local_space: body_b.world_space_to_body_space(&pt_on_b),
+        normal: *world_normal,
+        separation_distance,
+        time_of_impact: 0.0,
+        from_ccd: false,
+        body_a: handle_a,
+        body_b: handle_b
+    }
+}
@@ -0,0 +1,118 @@
+use crate::physics::body::Body;
+use crate::physics::bounds::Aabb;
+use crate::physics::collision::gjk::{
+    gjk,
+    GjkResult,
+    PointSupport
+};
+use crate::physics::collision::mesh::{
+    closest_point_on_triangle,
+    TriangleCollider
+};
+use crate::physics::quat::quat::Quat;
+use crate::physics::shape::{
+    Shape,
+    ShapeT
+};
+use crate::physics::vec::vec3d::Vec3d;
+
+/// Point of the body closest to the given point, in world space.
+/// Points inside a solid body are their own closest point, triangle meshes and heightfields only have a surface.
+pub fn closest_point_on_body(body: &Body, point: &Vec3d) -> Vec3d {
+    match &body.shape {
+        ShapeT::SphereShape { radius, .. } => {
+            let center: Vec3d = body.get_center_of_mass_world_space();
+            let delta: Vec3d = *point - center;
+            let distance: f64 = delta.get_magnitude();
+            if distance <= *radius {
+                return *point;
+            }
+            center + delta * (radius / distance)
+        },
+
+        ShapeT::BoxShape { width, length, height, .. } => {
+            // Clamp in the box's space, where it is axis aligned around the origin
+            let half_extents: Vec3d = Vec3d::new(*width, *length, *height) * 0.5;
+            let mut closest: Vec3d = body.world_space_to_body_space(point);
+            for i in 0..3 {
+                closest[i] = closest[i].clamp(-half_extents[i], half_extents[i]);
+            }
+            body.body_space_to_world_space(&closest)
+        },
+
+        ShapeT::PlaneShape { normal, offset } => {
+            let world_normal: Vec3d = body.orientation.rotate_point(*normal);
+            let height: f64 = world_normal.dot(*point - body.position) - offset;
+            if height <= 0.0 {
+                return *point;
+            }
+            *point - world_normal * height
+        },
+
+        ShapeT::TriangleMeshShape { mesh } =>
+            closest_point_on_triangles(body, mesh.as_ref(), point),
+
+        ShapeT::HeightfieldShape { field } =>
+            closest_point_on_triangles(body, field.as_ref(), point),
+
+        ShapeT::CompoundShape { children, .. } =>
+            children
+                .iter()
+                .map(|child| closest_point_on_body(&body.child_body(child), point))
+                .min_by(|a, b| (*a - *point).get_magnitude_squared().total_cmp(&(*b - *point).get_magnitude_squared()))
+                .unwrap_or(*point),
+
+        // Everything else is convex
+        _ => {
+            let result: GjkResult = gjk(&PointSupport { point: *point }, body);
+            if result.intersects {
+                return *point;
+            }
+            result.pt_on_b
+        }
+    }
+}
+
+/// Closest point on the triangles near the point. The search box grows until it holds
+/// a triangle point no further than the box's half size, nothing outside the box can be closer.
+fn closest_point_on_triangles(body: &Body, mesh: &impl TriangleCollider, point: &Vec3d) -> Vec3d {
+    let inverse_orient: Quat = body.orientation.inverse();
+    let local_point: Vec3d = inverse_orient.rotate_point(*point - body.position);
+    let mesh_bounds: Aabb = body.shape.get_bounds_local();
+
+    // A mesh without triangles has nothing to be close to
+    if (0..3).any(|i: usize| mesh_bounds.mins[i] > mesh_bounds.maxs[i]) {
+        return *point;
+    }
+
+    // Start at the distance to the mesh's bounds
+    let mut closest_in_bounds: Vec3d = local_point;
+    for i in 0..3 {
+        closest_in_bounds[i] = closest_in_bounds[i].clamp(mesh_bounds.mins[i], mesh_bounds.maxs[i]);
+    }
+    let mut reach: f64 = (closest_in_bounds - local_point).get_magnitude().max(f64::EPSILON);
+
+    loop {
+        let extent: Vec3d = Vec3d::new(reach, reach, reach);
+        let search: Aabb = Aabb::new(local_point - extent, local_point + extent);
+
+        let closest: Option<(Vec3d, f64)> = mesh
+            .query_bounds(&search)
+            .into_iter()
+            .map(|triangle| closest_point_on_triangle(&mesh.get_triangle_points(triangle), &local_point))
+            .map(|closest| (closest, (closest - local_point).get_magnitude()))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        match closest {
+            Some((closest, distance)) if distance <= reach =>
+                return body.position + body.orientation.rotate_point(closest),
+
+            // The search already covers the whole mesh
+            _ if search.contains(&mesh_bounds) =>
+                return closest.map_or(*point, |(closest, _)| body.position + body.orientation.rotate_point(closest)),
+
+            Some((_, distance)) => reach = distance,
+            None => reach *= 2.0
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use crate::physics::body::Body;
+use crate::physics::collision::gjk::{
+    gjk,
+    GjkResult,
+    PointSupport
+};
+use crate::physics::collision::toi::ray_sphere;
+use crate::physics::quat::quat::Quat;
+use crate::physics::scene::BodyHandle;
+use crate::physics::shape::ShapeT;
+use crate::physics::vec::vec3d::Vec3d;
+
+const MAX_RAY_ITERATIONS: usize = 32;
+const RAY_TOLERANCE: f64 = 0.0001; // Rays closer than this to a shape hit it
+
+/// Where a ray first hits a body of the scene
+#[derive(Debug, Clone, Copy)]
+pub struct RaycastHit {
+    pub body: BodyHandle,
+    pub point: Vec3d,
+    pub normal: Vec3d, // Surface normal, facing the ray
+    pub distance: f64, // Along the ray, from its origin
+    pub fraction: f64 // Distance to the hit over the ray's max distance, 0 for a ray without one
+}
+
+/// Where a swept shape first touches a body of the scene
+#[derive(Debug, Clone, Copy)]
+pub struct ShapeCastHit {
+    pub body: BodyHandle,
+    pub point: Vec3d, // On the hit body
+    pub normal: Vec3d, // From the hit body towards the swept shape
+    pub distance: f64, // Swept before the hit
+    pub fraction: f64 // Distance swept before the hit over the max distance, 0 for a sweep without one
+}
+
+/// Share of max_dist covered by distance, casts without a finite max distance have none
+pub fn get_cast_fraction(distance: f64, max_dist: f64) -> f64 {
+    if max_dist.is_finite() && max_dist > 0.0 {
+        distance / max_dist
+    } else {
+        0.0
+    }
+}
+
+/// Distance along a ray to where it first hits the body, and the surface normal there.
+/// A ray starting inside the body hits it right away, with the normal facing back along the ray.
+/// dir is expected to be normalized.
+pub fn ray_body(body: &Body, origin: &Vec3d, dir: &Vec3d, max_dist: f64) -> Option<(f64, Vec3d)> {
+    // Test in the body's space, the rotation keeps distances along the ray
+    let inverse_orient: Quat = body.orientation.inverse();
+    let local_origin: Vec3d = inverse_orient.rotate_point(*origin - body.position);
+    let local_dir: Vec3d = inverse_orient.rotate_point(*dir);
+
+    let (distance, local_normal): (f64, Vec3d) = match &body.shape {
+        ShapeT::SphereShape { radius, center_mass } =>
+            ray_sphere_shape(&local_origin, &local_dir, center_mass, *radius, max_dist)?,
+
+        ShapeT::BoxShape { width, length, height, .. } =>
+            ray_box(&local_origin, &local_dir, Vec3d::new(*width, *length, *height) * 0.5, max_dist)?,
+
+        ShapeT::PlaneShape { normal, offset } =>
+            ray_plane(&local_origin, &local_dir, normal, *offset, max_dist)?,
+
+        ShapeT::TriangleMeshShape { mesh } =>
+            mesh.raycast(&local_origin, &local_dir, max_dist)?,
+
+        ShapeT::HeightfieldShape { field } =>
+            field.raycast(&local_origin, &local_dir, max_dist)?,
+
+        // The nearest child hit is the hit
+        ShapeT::CompoundShape { children, .. } =>
+            return children
+                .iter()
+                .filter_map(|child| ray_body(&body.child_body(child), origin, dir, max_dist))
+                .min_by(|a, b| a.0.total_cmp(&b.0)),
+
+        // Everything else is convex
+        _ => return ray_convex(body, origin, dir, max_dist)
+    };
+
+    Some((distance, body.orientation.rotate_point(local_normal)))
+}
+
+fn ray_sphere_shape(origin: &Vec3d, dir: &Vec3d, center: &Vec3d, radius: f64, max_dist: f64) -> Option<(f64, Vec3d)> {
+    let (t0, t1): (f64, f64) = ray_sphere(*origin, *dir, *center, radius)?;
+    if t1 < 0.0 || t0 > max_dist {
+        return None;
+    }
+
+    // Starting inside
+    if t0 < 0.0 {
+        return Some((0.0, -*dir));
+    }
+
+    let point: Vec3d = *origin + *dir * t0;
+    Some((t0, (point - *center) / radius))
+}
+
+/// Slab test against a box centered on the origin, keeping track of the face the ray enters through
+fn ray_box(origin: &Vec3d, dir: &Vec3d, half_extents: Vec3d, max_dist: f64) -> Option<(f64, Vec3d)> {
+    let mut t_min: f64 = 0.0;
+    let mut t_max: f64 = max_dist;
+    let mut normal: Vec3d = -*dir; // Starting inside
+
+    for i in 0..3 {
+        if f64::abs(dir[i]) < f64::EPSILON {
+            // Parallel to the slab, the ray has to start between its planes
+            if f64::abs(origin[i]) > half_extents[i] {
+                return None;
+            }
+            continue;
+        }
+
+        // The ray enters the slab through the face looking against it
+        let inv_dir: f64 = 1.0 / dir[i];
+        let half_extent: f64 = half_extents[i];
+        let mut t1: f64 = (-half_extent - origin[i]) * inv_dir;
+        let mut t2: f64 = (half_extent - origin[i]) * inv_dir;
+        let mut side: f64 = -1.0;
+        if t1 > t2 {
+            std::mem::swap(&mut t1, &mut t2);
+            side = 1.0;
+        }
+
+        if t1 > t_min {
+            t_min = t1;
+            normal = Vec3d::zero();
+            normal[i] = side;
+        }
+        t_max = t_max.min(t2);
+
+        if t_min > t_max {
+            return None;
+        }
+    }
+
+    Some((t_min, normal))
+}
+
+/// The plane is the surface of a half-space, rays starting behind it are inside
+fn ray_plane(origin: &Vec3d, dir: &Vec3d, normal: &Vec3d, offset: f64, max_dist: f64) -> Option<(f64, Vec3d)> {
+    let height: f64 = normal.dot(*origin) - offset;
+    if height <= 0.0 {
+        return Some((0.0, -*dir));
+    }
+
+    // Moving along the plane or away from it
+    let speed: f64 = normal.dot(*dir);
+    if speed >= 0.0 {
+        return None;
+    }
+
+    let distance: f64 = height / -speed;
+    if distance > max_dist {
+        return None;
+    }
+    Some((distance, *normal))
+}
+
+/// Advances a point along the ray by its distance to the shape, measured with GJK.
+/// Rays are tested against convex shapes as a point moving along the ray.
+/// The shape is entirely behind the plane through the closest point, so the ray can't hit it before reaching that plane.
+fn ray_convex(body: &Body, origin: &Vec3d, dir: &Vec3d, max_dist: f64) -> Option<(f64, Vec3d)> {
+    let mut distance: f64 = 0.0;
+    let mut normal: Vec3d = -*dir;
+
+    for _ in 0..MAX_RAY_ITERATIONS {
+        let point: PointSupport = PointSupport { point: *origin + *dir * distance };
+        let result: GjkResult = gjk(&point, body);
+        if result.intersects || result.distance < RAY_TOLERANCE {
+            return Some((distance, normal));
+        }
+
+        // From the shape towards the ray
+        normal = (result.pt_on_a - result.pt_on_b).normalize();
+        let closing_speed: f64 = -dir.dot(normal);
+        if closing_speed <= 0.0 {
+            return None;
+        }
+
+        distance += result.distance / closing_speed;
+        if distance > max_dist {
+            return None;
+        }
+    }
+
+    None
+}
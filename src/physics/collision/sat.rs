@@ -0,0 +1,311 @@
+use crate::physics::body::Body;
+use crate::physics::collision::contact::Contact;
+use crate::physics::scene::BodyHandle;
+use crate::physics::vec::vec3d::Vec3d;
+
+// Edge and second-box axes only win over a face of the first box when they are clearly better,
+// this keeps the manifold from flickering between axes of nearly equal penetration
+const AXIS_TOLERANCE: f64 = 0.95;
+const MAX_MANIFOLD_POINTS: usize = 4;
+
+/// A box in world space, described by its center, rotated axes and half extents
+#[derive(Debug, Clone, Copy)]
+struct Obb {
+    center: Vec3d,
+    axes: [Vec3d; 3],
+    half_extents: Vec3d
+}
+
+impl Obb {
+    fn from_body(body: &Body, half_extents: Vec3d) -> Self {
+        Obb {
+            center: body.get_center_of_mass_world_space(),
+            axes: [
+                body.orientation.rotate_point(Vec3d::new(1.0, 0.0, 0.0)),
+                body.orientation.rotate_point(Vec3d::new(0.0, 1.0, 0.0)),
+                body.orientation.rotate_point(Vec3d::new(0.0, 0.0, 1.0))
+            ],
+            half_extents
+        }
+    }
+
+    /// Half the length of the box projected on an axis
+    fn project(&self, axis: Vec3d) -> f64 {
+        (0..3).map(|i| self.half_extents[i] * self.axes[i].dot(axis).abs()).sum()
+    }
+
+    /// Corners of the face whose outward normal is `sign * axes[axis]`, in winding order
+    fn face(&self, axis: usize, sign: f64) -> Vec<Vec3d> {
+        let (j, k) = ((axis + 1) % 3, (axis + 2) % 3);
+        let face_center: Vec3d = self.center + self.axes[axis] * (sign * self.half_extents[axis]);
+        let u: Vec3d = self.axes[j] * self.half_extents[j];
+        let v: Vec3d = self.axes[k] * self.half_extents[k];
+
+        vec![
+            face_center + u + v,
+            face_center - u + v,
+            face_center - u - v,
+            face_center + u - v
+        ]
+    }
+
+    /// The edge parallel to `axes[axis]` that lies furthest along `dir`
+    fn support_edge(&self, axis: usize, dir: Vec3d) -> (Vec3d, Vec3d) {
+        let mut mid: Vec3d = self.center;
+        for i in 0..3 {
+            if i == axis {
+                continue;
+            }
+            let sign: f64 = if self.axes[i].dot(dir) >= 0.0 { 1.0 } else { -1.0 };
+            mid += self.axes[i] * (sign * self.half_extents[i]);
+        }
+
+        let half_edge: Vec3d = self.axes[axis] * self.half_extents[axis];
+        (mid - half_edge, mid + half_edge)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SeparatingAxis {
+    FaceA(usize),
+    FaceB(usize),
+    Edge(usize, usize)
+}
+
+/// Box-box contact manifold using the separating axis test over the 15 candidate axes
+pub fn box_box(handle_a: BodyHandle, body_a: &Body, half_extents_a: Vec3d,
+               handle_b: BodyHandle, body_b: &Body, half_extents_b: Vec3d) -> Vec<Contact> {
+    let box_a: Obb = Obb::from_body(body_a, half_extents_a);
+    let box_b: Obb = Obb::from_body(body_b, half_extents_b);
+    let ab: Vec3d = box_b.center - box_a.center;
+
+    // Returns the penetration along the axis, negative when the boxes are separated on it
+    let penetration = |axis: Vec3d| -> f64 {
+        box_a.project(axis) + box_b.project(axis) - ab.dot(axis).abs()
+    };
+
+    let mut best_axis: SeparatingAxis = SeparatingAxis::FaceA(0);
+    let mut best_penetration: f64 = f64::MAX;
+
+    for i in 0..3 {
+        let depth: f64 = penetration(box_a.axes[i]);
+        if depth < 0.0 {
+            return Vec::new();
+        }
+        if depth < best_penetration {
+            best_penetration = depth;
+            best_axis = SeparatingAxis::FaceA(i);
+        }
+    }
+
+    for i in 0..3 {
+        let depth: f64 = penetration(box_b.axes[i]);
+        if depth < 0.0 {
+            return Vec::new();
+        }
+        if depth < best_penetration * AXIS_TOLERANCE {
+            best_penetration = depth;
+            best_axis = SeparatingAxis::FaceB(i);
+        }
+    }
+
+    for i in 0..3 {
+        for j in 0..3 {
+            let cross: Vec3d = box_a.axes[i].cross(box_b.axes[j]);
+            let length: f64 = cross.get_magnitude();
+
+            // Parallel edges, this axis is already covered by the face axes
+            if length < 1e-6 {
+                continue;
+            }
+
+            let depth: f64 = penetration(cross / length);
+            if depth < 0.0 {
+                return Vec::new();
+            }
+            if depth < best_penetration * AXIS_TOLERANCE {
+                best_penetration = depth;
+                best_axis = SeparatingAxis::Edge(i, j);
+            }
+        }
+    }
+
+    // The contact normal always points from A to B
+    let axis: Vec3d = match best_axis {
+        SeparatingAxis::FaceA(i) => box_a.axes[i],
+        SeparatingAxis::FaceB(i) => box_b.axes[i],
+        SeparatingAxis::Edge(i, j) => box_a.axes[i].cross(box_b.axes[j]).normalize()
+    };
+    let normal: Vec3d = if axis.dot(ab) < 0.0 { -axis } else { axis };
+
+    let make_contact = |pt_on_a: Vec3d, pt_on_b: Vec3d, separation_distance: f64| -> Contact {
+        Contact {
+            pt_on_a_world_space: pt_on_a,
+            pt_on_b_world_space: pt_on_b,
+            pt_on_a_local_space: body_a.world_space_to_body_space(&pt_on_a),
+            pt_on_b_local_space: body_b.world_space_to_body_space(&pt_on_b),
+            normal,
+            separation_distance,
+            time_of_impact: 0.0,
+            from_ccd: false,
+            body_a: handle_a,
+            body_b: handle_b
+        }
+    };
+
+    match best_axis {
+        SeparatingAxis::FaceA(i) => {
+            // A holds the reference face, the clipped points lie on B
+            face_contacts(&box_a, i, normal, &box_b)
+                .into_iter()
+                .map(|(point, separation)| make_contact(point - normal * separation, point, separation))
+                .collect()
+        },
+
+        SeparatingAxis::FaceB(i) => {
+            // B holds the reference face, its outward normal points back towards A
+            face_contacts(&box_b, i, -normal, &box_a)
+                .into_iter()
+                .map(|(point, separation)| make_contact(point, point + normal * separation, separation))
+                .collect()
+        },
+
+        SeparatingAxis::Edge(i, j) => {
+            let edge_a: (Vec3d, Vec3d) = box_a.support_edge(i, normal);
+            let edge_b: (Vec3d, Vec3d) = box_b.support_edge(j, -normal);
+            let (pt_on_a, pt_on_b) = closest_points_segment_segment(edge_a, edge_b);
+            vec![make_contact(pt_on_a, pt_on_b, -best_penetration)]
+        }
+    }
+}
+
+/// Clips the incident face of `incident` against the reference face of `reference`.
+/// Returns the clipped points on the incident box with their signed distance to the reference face.
+fn face_contacts(reference: &Obb, axis: usize, ref_normal: Vec3d, incident: &Obb) -> Vec<(Vec3d, f64)> {
+    // The incident face is the one most anti-parallel to the reference normal
+    let mut incident_axis: usize = 0;
+    let mut max_alignment: f64 = -1.0;
+    for i in 0..3 {
+        let alignment: f64 = incident.axes[i].dot(ref_normal).abs();
+        if alignment > max_alignment {
+            max_alignment = alignment;
+            incident_axis = i;
+        }
+    }
+    let incident_sign: f64 = if incident.axes[incident_axis].dot(ref_normal) > 0.0 { -1.0 } else { 1.0 };
+    let mut polygon: Vec<Vec3d> = incident.face(incident_axis, incident_sign);
+
+    // Clip against the four side planes of the reference face
+    for side in [(axis + 1) % 3, (axis + 2) % 3] {
+        let side_normal: Vec3d = reference.axes[side];
+        let offset: f64 = side_normal.dot(reference.center);
+        polygon = clip_polygon(&polygon, side_normal, offset + reference.half_extents[side]);
+        polygon = clip_polygon(&polygon, -side_normal, -offset + reference.half_extents[side]);
+    }
+
+    // Only keep the points that are below the reference face
+    let face_offset: f64 = ref_normal.dot(reference.center) + reference.half_extents[axis];
+    let points: Vec<(Vec3d, f64)> = polygon
+        .into_iter()
+        .map(|point| (point, ref_normal.dot(point) - face_offset))
+        .filter(|(_, separation)| *separation <= 0.0)
+        .collect();
+
+    reduce_manifold(points)
+}
+
+/// Sutherland-Hodgman clipping, keeps the part of the polygon where normal . p <= offset
+fn clip_polygon(polygon: &[Vec3d], normal: Vec3d, offset: f64) -> Vec<Vec3d> {
+    let mut clipped: Vec<Vec3d> = Vec::new();
+
+    for (i, &start) in polygon.iter().enumerate() {
+        let end: Vec3d = polygon[(i + 1) % polygon.len()];
+        let dist_start: f64 = normal.dot(start) - offset;
+        let dist_end: f64 = normal.dot(end) - offset;
+
+        if dist_start <= 0.0 {
+            clipped.push(start);
+        }
+
+        // The edge crosses the plane
+        if (dist_start < 0.0 && dist_end > 0.0) || (dist_start > 0.0 && dist_end < 0.0) {
+            let t: f64 = dist_start / (dist_start - dist_end);
+            clipped.push(start + (end - start) * t);
+        }
+    }
+
+    clipped
+}
+
+/// Keeps the deepest point, then repeatedly adds the point furthest from the ones already kept
+fn reduce_manifold(mut points: Vec<(Vec3d, f64)>) -> Vec<(Vec3d, f64)> {
+    if points.len() <= MAX_MANIFOLD_POINTS {
+        return points;
+    }
+
+    let deepest: usize = (0..points.len())
+        .min_by(|&i, &j| points[i].1.total_cmp(&points[j].1))
+        .unwrap_or(0);
+    let mut kept: Vec<(Vec3d, f64)> = vec![points.swap_remove(deepest)];
+
+    while kept.len() < MAX_MANIFOLD_POINTS && !points.is_empty() {
+        let distance_to_kept = |point: &Vec3d| -> f64 {
+            kept.iter()
+                .map(|(kept_point, _)| (*point - *kept_point).get_magnitude())
+                .fold(f64::MAX, f64::min)
+        };
+
+        let furthest: usize = (0..points.len())
+            .max_by(|&i, &j| distance_to_kept(&points[i].0).total_cmp(&distance_to_kept(&points[j].0)))
+            .unwrap_or(0);
+        kept.push(points.swap_remove(furthest));
+    }
+
+    kept
+}
+
+/// Closest points between the segments (p1, q1) and (p2, q2)
+pub fn closest_points_segment_segment((p1, q1): (Vec3d, Vec3d), (p2, q2): (Vec3d, Vec3d)) -> (Vec3d, Vec3d) {
+    let d1: Vec3d = q1 - p1;
+    let d2: Vec3d = q2 - p2;
+    let r: Vec3d = p1 - p2;
+    let a: f64 = d1.dot(d1);
+    let e: f64 = d2.dot(d2);
+    let f: f64 = d2.dot(r);
+
+    // Both segments degenerate into points
+    if a <= f64::EPSILON && e <= f64::EPSILON {
+        return (p1, p2);
+    }
+
+    let (s, t): (f64, f64) = if a <= f64::EPSILON {
+        (0.0, (f / e).clamp(0.0, 1.0))
+    } else {
+        let c: f64 = d1.dot(r);
+        if e <= f64::EPSILON {
+            ((-c / a).clamp(0.0, 1.0), 0.0)
+        } else {
+            let b: f64 = d1.dot(d2);
+            let denom: f64 = a * e - b * b;
+
+            // Parallel segments have no unique answer, start from the beginning of the first one
+            let mut s: f64 = if denom > 0.0 {
+                ((b * f - c * e) / denom).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+
+            let mut t: f64 = (b * s + f) / e;
+            if t < 0.0 {
+                t = 0.0;
+                s = (-c / a).clamp(0.0, 1.0);
+            } else if t > 1.0 {
+                t = 1.0;
+                s = ((b - c) / a).clamp(0.0, 1.0);
+            }
+            (s, t)
+        }
+    };
+
+    (p1 + d1 * s, p2 + d2 * t)
+}
@@ -0,0 +1,198 @@
+use crate::physics::body::{
+    Body,
+    BodyType
+};
+use crate::physics::broadphase::swept_bounds;
+use crate::physics::collision::contact::Contact;
+use crate::physics::collision::intersect::{
+    closest_points,
+    intersect
+};
+use crate::physics::collision::mesh::{
+    mesh_candidates,
+    triangle_closest_points,
+    TriangleCollider
+};
+use crate::physics::scene::BodyHandle;
+use crate::physics::shape::ShapeT;
+use crate::physics::vec::vec3d::Vec3d;
+
+const MAX_ADVANCEMENT_ITERATIONS: usize = 10;
+const CONTACT_TOLERANCE: f64 = 0.001; // Bodies closer than this count as touching
+
+/// Continuous narrowphase, finds the first contact between two bodies during the next dt_sec.
+/// Contacts carry their time of impact, bodies are expected at their start-of-step state.
+pub fn intersect_dynamic(handle_a: BodyHandle, body_a: &Body, handle_b: BodyHandle, body_b: &Body,
+                         dt_sec: f64) -> Vec<Contact> {
+    match (&body_a.shape, &body_b.shape) {
+        (ShapeT::SphereShape { radius: radius_a, .. }, ShapeT::SphereShape { radius: radius_b, .. }) =>
+            sphere_sphere_dynamic(handle_a, body_a, *radius_a, handle_b, body_b, *radius_b, dt_sec)
+                .into_iter()
+                .collect(),
+
+        (shape_a, shape_b) if shape_a.is_static_only() && shape_b.is_static_only() => Vec::new(),
+
+        (ShapeT::TriangleMeshShape { mesh }, _) =>
+            triangles_dynamic(handle_a, body_a, mesh.as_ref(), handle_b, body_b, dt_sec),
+
+        (ShapeT::HeightfieldShape { field }, _) =>
+            triangles_dynamic(handle_a, body_a, field.as_ref(), handle_b, body_b, dt_sec),
+
+        (_, shape_b) if shape_b.is_static_only() =>
+            intersect_dynamic(handle_b, body_b, handle_a, body_a, dt_sec)
+                .into_iter()
+                .map(|contact| contact.swapped())
+                .collect(),
+
+        _ => conservative_advancement(handle_a, body_a, handle_b, body_b, dt_sec,
+                                      |body_a, body_b| closest_points(handle_a, body_a, handle_b, body_b))
+    }
+}
+
+/// Returns the ray parameters where the ray enters and exits the sphere, dir doesn't need to be normalized
+pub fn ray_sphere(ray_start: Vec3d, ray_dir: Vec3d, sphere_center: Vec3d, sphere_radius: f64) -> Option<(f64, f64)> {
+    // Solve |start + dir * t - center|^2 = r^2 for t
+    let m: Vec3d = sphere_center - ray_start;
+    let a: f64 = ray_dir.dot(ray_dir);
+    let b: f64 = m.dot(ray_dir);
+    let c: f64 = m.dot(m) - sphere_radius * sphere_radius;
+
+    let delta: f64 = b * b - a * c;
+    if delta < 0.0 {
+        return None;
+    }
+
+    let delta_root: f64 = delta.sqrt();
+    let inv_a: f64 = 1.0 / a;
+    Some(((b - delta_root) * inv_a, (b + delta_root) * inv_a))
+}
+
+fn sphere_sphere_dynamic(handle_a: BodyHandle, body_a: &Body, radius_a: f64,
+                         handle_b: BodyHandle, body_b: &Body, radius_b: f64, dt_sec: f64) -> Option<Contact> {
+    // Sweep sphere A against B as if B was standing still
+    let relative_velocity: Vec3d = body_a.linear_velocity - body_b.linear_velocity;
+    let ray_dir: Vec3d = relative_velocity * dt_sec;
+    let radius_ab: f64 = radius_a + radius_b;
+
+    let (t0, t1): (f64, f64) = if ray_dir.get_magnitude() < CONTACT_TOLERANCE {
+        // The spheres barely move relative to each other, check if they overlap right now
+        let ab: Vec3d = body_b.position - body_a.position;
+        if ab.get_magnitude() > radius_ab + CONTACT_TOLERANCE {
+            return None;
+        }
+        (0.0, 0.0)
+    } else {
+        ray_sphere(body_a.position, ray_dir, body_b.position, radius_ab)?
+    };
+
+    // Change from [0, 1] to [0, dt]
+    let (t0, t1) = (t0 * dt_sec, t1 * dt_sec);
+
+    // The collision only happened in the past or happens after this step
+    if t1 < 0.0 || t0 > dt_sec {
+        return None;
+    }
+
+    // Already overlapping bodies collide right away
+    let time_of_impact: f64 = t0.max(0.0);
+
+    let new_pos_a: Vec3d = body_a.position + body_a.linear_velocity * time_of_impact;
+    let new_pos_b: Vec3d = body_b.position + body_b.linear_velocity * time_of_impact;
+    let ab: Vec3d = new_pos_b - new_pos_a;
+    let distance: f64 = ab.get_magnitude();
+    let normal: Vec3d = if distance > 0.0 {
+        ab / distance
+    } else {
+        Vec3d::new(0.0, 1.0, 0.0)
+    };
+
+    let pt_on_a: Vec3d = new_pos_a + normal * radius_a;
+    let pt_on_b: Vec3d = new_pos_b - normal * radius_b;
+
+    // Local points are relative to the bodies at the time of impact
+    let mut body_a_at_toi: Body = body_a.clone();
+    let mut body_b_at_toi: Body = body_b.clone();
+    body_a_at_toi.position = new_pos_a;
+    body_b_at_toi.position = new_pos_b;
+
+    Some(Contact {
+        pt_on_a_world_space: pt_on_a,
+        pt_on_b_world_space: pt_on_b,
+        pt_on_a_local_space: body_a_at_toi.world_space_to_body_space(&pt_on_a),
+        pt_on_b_local_space: body_b_at_toi.world_space_to_body_space(&pt_on_b),
+        normal,
+        separation_distance: distance - radius_ab,
+        time_of_impact,
+        from_ccd: t0 > 0.0,
+        body_a: handle_a,
+        body_b: handle_b
+    })
+}
+
+/// Each triangle the body can reach is convex on its own, the first one it hits stops it
+fn triangles_dynamic(handle_a: BodyHandle, body_a: &Body, mesh: &impl TriangleCollider,
+                     handle_b: BodyHandle, body_b: &Body, dt_sec: f64) -> Vec<Contact> {
+    mesh_candidates(body_a, mesh, &swept_bounds(body_b, dt_sec))
+        .into_iter()
+        .flat_map(|triangle| conservative_advancement(handle_a, body_a, handle_b, body_b, dt_sec,
+            |body_a, body_b| triangle_closest_points(handle_a, body_a, mesh, triangle, handle_b, body_b)))
+        .min_by(|a, b| a.time_of_impact.total_cmp(&b.time_of_impact))
+        .into_iter()
+        .collect()
+}
+
+/// Steps both bodies forward by the largest time that can't make them pass through each other,
+/// until they touch or the step runs out. closest_points measures the bodies as they advance.
+/// Pairs it can't measure fall back to the discrete test.
+fn conservative_advancement(handle_a: BodyHandle, body_a: &Body, handle_b: BodyHandle, body_b: &Body, dt_sec: f64,
+                            closest_points: impl Fn(&Body, &Body) -> Option<Contact>) -> Vec<Contact> {
+    // Advance copies, the scene moves the real bodies once the contacts are sorted
+    let mut body_a: Body = body_a.clone();
+    let mut body_b: Body = body_b.clone();
+
+    let mut time_of_impact: f64 = 0.0;
+    let mut time_remaining: f64 = dt_sec;
+    let mut advanced: bool = false;
+
+    for _ in 0..MAX_ADVANCEMENT_ITERATIONS {
+        let Some(mut contact) = closest_points(&body_a, &body_b) else {
+            return intersect(handle_a, &body_a, handle_b, &body_b);
+        };
+
+        if contact.separation_distance < CONTACT_TOLERANCE {
+            // Bodies touching before any advancement are resting on each other
+            contact.time_of_impact = time_of_impact;
+            contact.from_ccd = advanced;
+            return vec![contact];
+        }
+
+        // Project the relative velocity on the direction of the closest points,
+        // and add the fastest any point can move along it because of the rotation
+        let ab: Vec3d = contact.normal;
+        let relative_velocity: Vec3d = body_a.linear_velocity - body_b.linear_velocity;
+        let ortho_speed: f64 = relative_velocity.dot(ab)
+            + body_a.fastest_linear_speed(&ab)
+            + body_b.fastest_linear_speed(&-ab);
+
+        // Moving apart
+        if ortho_speed <= 0.0 {
+            break;
+        }
+
+        let time_to_go: f64 = contact.separation_distance / ortho_speed;
+        if time_to_go > time_remaining {
+            break;
+        }
+
+        time_remaining -= time_to_go;
+        time_of_impact += time_to_go;
+        advanced = true;
+        for body in [&mut body_a, &mut body_b] {
+            if body.body_type != BodyType::Static {
+                body.update(time_to_go);
+            }
+        }
+    }
+
+    Vec::new()
+}
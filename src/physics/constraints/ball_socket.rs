@@ -0,0 +1,91 @@
+use crate::physics::body::Body;
+use crate::physics::constraints::constraint::{
+    carry_impulses,
+    get_relative_orientation,
+    new_angle_limit_row,
+    new_point_rows,
+    Constraint,
+    ConstraintRow
+};
+use crate::physics::quat::quat::Quat;
+use crate::physics::scene::BodyHandle;
+use crate::physics::vec::vec3d::Vec3d;
+
+/// Pins an anchor on each body together, the bodies turn freely around it, like a shoulder.
+/// The turn can be limited to a cone around axis and a twist range around it, both measured on the
+/// orientation of B relative to A, away from the reference orientation.
+#[derive(Debug, Clone)]
+pub struct BallSocketConstraint {
+    pub body_a: BodyHandle,
+    pub body_b: BodyHandle,
+    pub anchor_a: Vec3d, // In A's body space
+    pub anchor_b: Vec3d, // In B's body space
+    pub axis: Vec3d, // In A's body space, the cone and the twist are around it
+    pub reference: Quat, // Orientation of B relative to A at rest
+    pub cone_limit: Option<f64>, // Largest angle off the axis, in radians
+    pub twist_limit: Option<(f64, f64)>, // Range of the angle around the axis, in radians
+    rows: Vec<ConstraintRow> // Found in pre_solve
+}
+
+impl BallSocketConstraint {
+    /// The anchors are in the body space of their bodies, the joint starts without limits
+    pub fn new(body_a: BodyHandle, anchor_a: Vec3d, body_b: BodyHandle, anchor_b: Vec3d, axis: Vec3d) -> Self {
+        BallSocketConstraint {
+            body_a,
+            body_b,
+            anchor_a,
+            anchor_b,
+            axis: axis.normalize(),
+            reference: Quat::zero(),
+            cone_limit: None,
+            twist_limit: None,
+            rows: Vec::new()
+        }
+    }
+
+    /// The cone row gets id 3 and the twist rows ids 4 and 5
+    fn add_limit_rows(&mut self, body_a: &Body, body_b: &Body, dt_sec: f64) {
+        if self.cone_limit.is_none() && self.twist_limit.is_none() {
+            return;
+        }
+
+        let relative: Quat = get_relative_orientation(body_a, body_b, &self.reference);
+        let (swing, twist): (Quat, Quat) = relative.swing_twist(self.axis);
+
+        if let Some(cone) = self.cone_limit {
+            // The swing turns around an axis across the joint axis, towards the edge of the cone
+            let swing_axis: Vec3d = swing.get_xyz() * f64::signum(swing.w);
+            // The swing angle is never negative, so the cone only limits it from above
+            let swing_angle: f64 = swing.get_angle();
+            if swing_axis.get_magnitude_squared() > f64::EPSILON && swing_angle > cone {
+                let swing_axis_world: Vec3d = body_a.orientation.rotate_point(swing_axis.normalize());
+                let mut row: ConstraintRow = ConstraintRow::new_angular(body_a, body_b, &-swing_axis_world, cone - swing_angle, dt_sec);
+                row.id = 3;
+                row.min_impulse = 0.0;
+                self.rows.push(row);
+            }
+        }
+
+        if let Some(limit) = self.twist_limit {
+            let axis_world: Vec3d = body_a.orientation.rotate_point(self.axis);
+            self.rows.extend(new_angle_limit_row(body_a, body_b, &axis_world, twist.get_twist_angle(self.axis), limit, 4, dt_sec));
+        }
+    }
+}
+
+impl Constraint for BallSocketConstraint {
+    fn get_bodies(&self) -> (BodyHandle, BodyHandle) {
+        (self.body_a, self.body_b)
+    }
+
+    fn pre_solve(&mut self, body_a: &Body, body_b: &Body, dt_sec: f64) {
+        let previous: Vec<ConstraintRow> = std::mem::take(&mut self.rows);
+        self.rows.extend(new_point_rows(body_a, body_b, &self.anchor_a, &self.anchor_b, dt_sec));
+        self.add_limit_rows(body_a, body_b, dt_sec);
+        carry_impulses(&previous, &mut self.rows);
+    }
+
+    fn get_rows_mut(&mut self) -> &mut [ConstraintRow] {
+        &mut self.rows
+    }
+}
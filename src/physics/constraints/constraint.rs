@@ -0,0 +1,194 @@
+use crate::physics::body::Body;
+use crate::physics::mat::mat3::Mat3;
+use crate::physics::quat::quat::Quat;
+use crate::physics::scene::BodyHandle;
+use crate::physics::vec::vec3d::Vec3d;
+
+pub const BAUMGARTE: f64 = 0.05; // Share of the position error corrected every step
+pub const SLOP: f64 = 0.001; // Position error left alone so joints don't jitter
+
+/// Joint or contact between two bodies of a scene, solved as impulses on their velocities.
+/// Every step, the solver calls pre_solve once, warm_start once, solve a few times, then post_solve once.
+pub trait Constraint: Send + Sync {
+    /// The two bodies the constraint joins
+    fn get_bodies(&self) -> (BodyHandle, BodyHandle);
+
+    /// Builds the rows of the Jacobian from the bodies at the start of the step, before any impulse is applied.
+    /// Rows keep the impulse of the row with the same id last step.
+    fn pre_solve(&mut self, body_a: &Body, body_b: &Body, dt_sec: f64);
+
+    /// Rows found by the last pre_solve
+    fn get_rows_mut(&mut self) -> &mut [ConstraintRow];
+
+    /// Applies the impulses the rows kept from last step, the solver then only has to correct them
+    fn warm_start(&mut self, body_a: &mut Body, body_b: &mut Body) {
+        for row in self.get_rows_mut() {
+            row.warm_start(body_a, body_b);
+        }
+    }
+
+    /// Applies the impulses that bring the velocities of the bodies back within the constraint
+    fn solve(&mut self, body_a: &mut Body, body_b: &mut Body) {
+        for row in self.get_rows_mut() {
+            row.solve(body_a, body_b);
+        }
+    }
+
+    /// Called once the step's impulses are all applied
+    fn post_solve(&mut self) {}
+}
+
+/// One row of a constraint's Jacobian, a speed along one direction the bodies must keep at zero.
+/// J * v = linear_a . v_a + angular_a . w_a + linear_b . v_b + angular_b . w_b
+#[derive(Debug, Clone, Copy)]
+pub struct ConstraintRow {
+    pub linear_a: Vec3d,
+    pub angular_a: Vec3d,
+    pub linear_b: Vec3d,
+    pub angular_b: Vec3d,
+    pub bias: f64, // Extra speed that corrects the position error
+    pub min_impulse: f64, // Bounds of the impulse over the whole step, a limit can only push
+    pub max_impulse: f64,
+    pub id: usize, // Same for the row along the same direction every step, to carry its impulse over
+    effective_mass: f64,
+    total_impulse: f64
+}
+
+impl ConstraintRow {
+    pub fn new(body_a: &Body, body_b: &Body, linear_a: Vec3d, angular_a: Vec3d, linear_b: Vec3d, angular_b: Vec3d, bias: f64) -> Self {
+        // K = J * M^-1 * J^T
+        let inv_inertia_a: Mat3 = body_a.get_inverse_inertia_tensor_world_space();
+        let inv_inertia_b: Mat3 = body_b.get_inverse_inertia_tensor_world_space();
+        let k: f64 = linear_a.get_magnitude_squared() * body_a.get_inv_mass()
+            + angular_a.dot(inv_inertia_a * angular_a)
+            + linear_b.get_magnitude_squared() * body_b.get_inv_mass()
+            + angular_b.dot(inv_inertia_b * angular_b);
+
+        ConstraintRow {
+            linear_a,
+            angular_a,
+            linear_b,
+            angular_b,
+            bias,
+            min_impulse: f64::MIN,
+            max_impulse: f64::MAX,
+            id: 0,
+            // Nothing can move along the row when both bodies have infinite mass
+            effective_mass: if k > f64::EPSILON { 1.0 / k } else { 0.0 },
+            total_impulse: 0.0
+        }
+    }
+
+    /// Keeps the anchor on B from moving away from the anchor on A along dir,
+    /// r_a and r_b go from the centers of mass to the anchors, error is how far apart they already are
+    pub fn new_linear(body_a: &Body, body_b: &Body, r_a: &Vec3d, r_b: &Vec3d, dir: &Vec3d, error: f64, dt_sec: f64) -> Self {
+        ConstraintRow::new(body_a, body_b, -*dir, -r_a.cross(*dir), *dir, r_b.cross(*dir), baumgarte_bias(error, dt_sec))
+    }
+
+    /// Keeps B from turning relative to A around axis, error is the angle it already turned by
+    pub fn new_angular(body_a: &Body, body_b: &Body, axis: &Vec3d, error: f64, dt_sec: f64) -> Self {
+        ConstraintRow::new(body_a, body_b, Vec3d::zero(), -*axis, Vec3d::zero(), *axis, baumgarte_bias(error, dt_sec))
+    }
+
+    /// Impulse applied along the row over the whole step
+    pub fn get_total_impulse(&self) -> f64 {
+        self.total_impulse
+    }
+
+    /// Forgets the impulse carried over from last step
+    pub fn clear_impulse(&mut self) {
+        self.total_impulse = 0.0;
+    }
+
+    /// Applies the impulse carried over from last step again
+    pub fn warm_start(&mut self, body_a: &mut Body, body_b: &mut Body) {
+        self.total_impulse = self.total_impulse.clamp(self.min_impulse, self.max_impulse);
+        self.apply_impulse(self.total_impulse, body_a, body_b);
+    }
+
+    pub fn solve(&mut self, body_a: &mut Body, body_b: &mut Body) {
+        if self.effective_mass == 0.0 {
+            return;
+        }
+
+        let jv: f64 = self.linear_a.dot(body_a.linear_velocity)
+            + self.angular_a.dot(body_a.angular_velocity)
+            + self.linear_b.dot(body_b.linear_velocity)
+            + self.angular_b.dot(body_b.angular_velocity);
+
+        // lambda = -(J * v + b) / K, kept within the bounds over the whole step
+        let lambda: f64 = -(jv + self.bias) * self.effective_mass;
+        let total: f64 = (self.total_impulse + lambda).clamp(self.min_impulse, self.max_impulse);
+        let lambda: f64 = total - self.total_impulse;
+        self.total_impulse = total;
+        self.apply_impulse(lambda, body_a, body_b);
+    }
+
+    fn apply_impulse(&self, lambda: f64, body_a: &mut Body, body_b: &mut Body) {
+        body_a.apply_impulse_linear(self.linear_a * lambda);
+        body_a.apply_impulse_angular(self.angular_a * lambda);
+        body_b.apply_impulse_linear(self.linear_b * lambda);
+        body_b.apply_impulse_angular(self.angular_b * lambda);
+    }
+}
+
+/// Rows keep the impulse of the previous row with the same id, the rest start from nothing
+pub fn carry_impulses(previous: &[ConstraintRow], rows: &mut [ConstraintRow]) {
+    for row in rows {
+        if let Some(previous_row) = previous.iter().find(|previous_row| previous_row.id == row.id) {
+            row.total_impulse = previous_row.total_impulse;
+        }
+    }
+}
+
+/// Baumgarte stabilization, feeds part of the position error back into the velocity
+pub fn baumgarte_bias(error: f64, dt_sec: f64) -> f64 {
+    let correction: f64 = if error > SLOP {
+        error - SLOP
+    } else if error < -SLOP {
+        error + SLOP
+    } else {
+        0.0
+    };
+    BAUMGARTE / dt_sec * correction
+}
+
+/// How far B is turned from its reference orientation relative to A, as a rotation in A's body space.
+/// reference is B's orientation relative to A when the joint is at rest.
+pub fn get_relative_orientation(body_a: &Body, body_b: &Body, reference: &Quat) -> Quat {
+    (body_a.orientation.inverse() * body_b.orientation * reference.inverse()).normalize()
+}
+
+/// Pushes B back within [min, max] radians around axis, only when it's already outside.
+/// The row pushing up from min gets id, the row pushing down from max gets id + 1
+pub fn new_angle_limit_row(body_a: &Body, body_b: &Body, axis: &Vec3d, angle: f64, (min, max): (f64, f64), id: usize, dt_sec: f64) -> Option<ConstraintRow> {
+    let (mut row, row_id): (ConstraintRow, usize) = if angle < min {
+        (ConstraintRow::new_angular(body_a, body_b, axis, angle - min, dt_sec), id)
+    } else if angle > max {
+        (ConstraintRow::new_angular(body_a, body_b, &-*axis, max - angle, dt_sec), id + 1)
+    } else {
+        return None;
+    };
+
+    row.id = row_id;
+    row.min_impulse = 0.0;
+    Some(row)
+}
+
+/// Keeps an anchor on each body at the same point, anchors are in the body space of their bodies.
+/// The rows get ids 0 to 2
+pub fn new_point_rows(body_a: &Body, body_b: &Body, anchor_a: &Vec3d, anchor_b: &Vec3d, dt_sec: f64) -> [ConstraintRow; 3] {
+    let point_a: Vec3d = body_a.body_space_to_world_space(anchor_a);
+    let point_b: Vec3d = body_b.body_space_to_world_space(anchor_b);
+    let r_a: Vec3d = point_a - body_a.get_center_of_mass_world_space();
+    let r_b: Vec3d = point_b - body_b.get_center_of_mass_world_space();
+    let separation: Vec3d = point_b - point_a;
+
+    // One row per world axis
+    let axes: [Vec3d; 3] = [Vec3d::new(1.0, 0.0, 0.0), Vec3d::new(0.0, 1.0, 0.0), Vec3d::new(0.0, 0.0, 1.0)];
+    std::array::from_fn(|id| {
+        let mut row: ConstraintRow = ConstraintRow::new_linear(body_a, body_b, &r_a, &r_b, &axes[id], separation.dot(axes[id]), dt_sec);
+        row.id = id;
+        row
+    })
+}
@@ -0,0 +1,71 @@
+use crate::physics::body::Body;
+use crate::physics::constraints::constraint::{
+    carry_impulses,
+    Constraint,
+    ConstraintRow
+};
+use crate::physics::scene::BodyHandle;
+use crate::physics::vec::vec3d::Vec3d;
+
+/// Holds two anchor points, one on each body, at a fixed distance from each other.
+/// Chains, pendulums and ropes are made of these.
+#[derive(Debug, Clone)]
+pub struct DistanceConstraint {
+    pub body_a: BodyHandle,
+    pub body_b: BodyHandle,
+    pub anchor_a: Vec3d, // In A's body space
+    pub anchor_b: Vec3d, // In B's body space
+    pub length: f64,
+    row: Option<ConstraintRow> // Found in pre_solve, None when the anchors meet
+}
+
+impl DistanceConstraint {
+    /// The anchors are in the body space of their bodies
+    pub fn new(body_a: BodyHandle, anchor_a: Vec3d, body_b: BodyHandle, anchor_b: Vec3d, length: f64) -> Self {
+        DistanceConstraint {
+            body_a,
+            body_b,
+            anchor_a,
+            anchor_b,
+            length,
+            row: None
+        }
+    }
+
+    /// Total impulse applied along the joint in the last step, positive when pulling the bodies together
+    pub fn get_impulse(&self) -> f64 {
+        self.row.map_or(0.0, |row| -row.get_total_impulse())
+    }
+}
+
+impl Constraint for DistanceConstraint {
+    fn get_bodies(&self) -> (BodyHandle, BodyHandle) {
+        (self.body_a, self.body_b)
+    }
+
+    fn pre_solve(&mut self, body_a: &Body, body_b: &Body, dt_sec: f64) {
+        let previous: Option<ConstraintRow> = self.row.take();
+
+        let point_a: Vec3d = body_a.body_space_to_world_space(&self.anchor_a);
+        let point_b: Vec3d = body_b.body_space_to_world_space(&self.anchor_b);
+        let r_a: Vec3d = point_a - body_a.get_center_of_mass_world_space();
+        let r_b: Vec3d = point_b - body_b.get_center_of_mass_world_space();
+
+        // The direction is undefined when the anchors meet
+        let separation: Vec3d = point_b - point_a;
+        let distance: f64 = separation.get_magnitude();
+        if distance < f64::EPSILON {
+            return;
+        }
+
+        // C = |p_b - p_a| - L
+        let normal: Vec3d = separation / distance;
+        let mut row: ConstraintRow = ConstraintRow::new_linear(body_a, body_b, &r_a, &r_b, &normal, distance - self.length, dt_sec);
+        carry_impulses(previous.as_slice(), std::slice::from_mut(&mut row));
+        self.row = Some(row);
+    }
+
+    fn get_rows_mut(&mut self) -> &mut [ConstraintRow] {
+        self.row.as_mut_slice()
+    }
+}
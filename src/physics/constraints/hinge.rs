@@ -0,0 +1,79 @@
+use crate::physics::body::Body;
+use crate::physics::constraints::constraint::{
+    carry_impulses,
+    get_relative_orientation,
+    new_angle_limit_row,
+    new_point_rows,
+    Constraint,
+    ConstraintRow
+};
+use crate::physics::quat::quat::Quat;
+use crate::physics::scene::BodyHandle;
+use crate::physics::vec::vec3d::Vec3d;
+
+/// Pins an anchor on each body together and only lets B turn around one axis of A, like a door or a wheel.
+/// The angle can be limited, it's measured on the orientation of B relative to A, away from the reference orientation.
+#[derive(Debug, Clone)]
+pub struct HingeConstraint {
+    pub body_a: BodyHandle,
+    pub body_b: BodyHandle,
+    pub anchor_a: Vec3d, // In A's body space
+    pub anchor_b: Vec3d, // In B's body space
+    pub axis: Vec3d, // In A's body space
+    pub reference: Quat, // Orientation of B relative to A at rest, where the angle is 0
+    pub angle_limit: Option<(f64, f64)>, // Range of the angle around the axis, in radians
+    rows: Vec<ConstraintRow> // Found in pre_solve
+}
+
+impl HingeConstraint {
+    /// The anchors are in the body space of their bodies, the hinge starts without limits
+    pub fn new(body_a: BodyHandle, anchor_a: Vec3d, body_b: BodyHandle, anchor_b: Vec3d, axis: Vec3d) -> Self {
+        HingeConstraint {
+            body_a,
+            body_b,
+            anchor_a,
+            anchor_b,
+            axis: axis.normalize(),
+            reference: Quat::zero(),
+            angle_limit: None,
+            rows: Vec::new()
+        }
+    }
+}
+
+impl Constraint for HingeConstraint {
+    fn get_bodies(&self) -> (BodyHandle, BodyHandle) {
+        (self.body_a, self.body_b)
+    }
+
+    fn pre_solve(&mut self, body_a: &Body, body_b: &Body, dt_sec: f64) {
+        let previous: Vec<ConstraintRow> = std::mem::take(&mut self.rows);
+        self.rows.extend(new_point_rows(body_a, body_b, &self.anchor_a, &self.anchor_b, dt_sec));
+
+        let relative: Quat = get_relative_orientation(body_a, body_b, &self.reference);
+        let (swing, twist): (Quat, Quat) = relative.swing_twist(self.axis);
+
+        // Lock the two axes across the hinge, the swing is how far B already turned around them,
+        // a small rotation's vector part is half its rotation vector. These rows get ids 3 and 4
+        let swing_vector: Vec3d = swing.get_xyz() * (2.0 * f64::signum(swing.w));
+        let mut u: Vec3d = Vec3d::zero();
+        let mut v: Vec3d = Vec3d::zero();
+        self.axis.get_ortho(&mut u, &mut v);
+        for (id, ortho) in [(3, u), (4, v)] {
+            let ortho_world: Vec3d = body_a.orientation.rotate_point(ortho);
+            let mut row: ConstraintRow = ConstraintRow::new_angular(body_a, body_b, &ortho_world, swing_vector.dot(ortho), dt_sec);
+            row.id = id;
+            self.rows.push(row);
+        }
+
+        if let Some(limit) = self.angle_limit {
+            let axis_world: Vec3d = body_a.orientation.rotate_point(self.axis);
+            self.rows.extend(new_angle_limit_row(body_a, body_b, &axis_world, twist.get_twist_angle(self.axis), limit, 5, dt_sec));
+        }
+        carry_impulses(&previous, &mut self.rows);
+    }
+
+    fn get_rows_mut(&mut self) -> &mut [ConstraintRow] {
+        &mut self.rows
+    }
+}
@@ -0,0 +1,153 @@
+use crate::physics::body::Body;
+use crate::physics::collision::contact::Contact;
+use crate::physics::constraints::penetration::PenetrationConstraint;
+use crate::physics::scene::BodyHandle;
+use crate::physics::vec::vec3d::Vec3d;
+use std::collections::BTreeMap;
+
+/// Contact points between two bodies, kept from step to step.
+/// Narrowphase often finds a single point per step, the manifold gathers them until the bodies rest on several,
+/// and every point keeps its impulses so the solver can warm start from them.
+#[derive(Debug, Clone)]
+pub struct Manifold {
+    pub body_a: BodyHandle,
+    pub body_b: BodyHandle,
+    pub contacts: Vec<PenetrationConstraint>
+}
+
+impl Manifold {
+    pub const MAX_CONTACTS: usize = 4;
+    pub const MATCH_DISTANCE: f64 = 0.02; // m, a new point closer than this to an old one replaces it
+    pub const BREAK_DISTANCE: f64 = 0.02; // m, points whose anchors drift further apart are dropped
+
+    pub fn new(body_a: BodyHandle, body_b: BodyHandle) -> Self {
+        Manifold {
+            body_a,
+            body_b,
+            contacts: Vec::new()
+        }
+    }
+
+    /// The contact is expected to be between the manifold's bodies, in the same order
+    pub fn add_contact(&mut self, contact: &Contact, body_a: &Body, body_b: &Body) {
+        let mut constraint: PenetrationConstraint = PenetrationConstraint::new(contact, body_a);
+
+        // The same point as an old one, seen again
+        let same_contact: Option<usize> = self.contacts.iter().position(|old| {
+            (old.anchor_a - constraint.anchor_a).get_magnitude() < Manifold::MATCH_DISTANCE ||
+            (old.anchor_b - constraint.anchor_b).get_magnitude() < Manifold::MATCH_DISTANCE
+        });
+        if let Some(index) = same_contact {
+            constraint.warm_start_from(&self.contacts[index]);
+            self.contacts[index] = constraint;
+            return;
+        }
+
+        self.contacts.push(constraint);
+        if self.contacts.len() > Manifold::MAX_CONTACTS {
+            self.reduce(body_a, body_b);
+        }
+    }
+
+    /// Drops the points whose anchors separated or slid apart since they were found
+    pub fn remove_expired(&mut self, body_a: &Body, body_b: &Body) {
+        self.contacts.retain(|contact| {
+            let (point_a, point_b, normal): (Vec3d, Vec3d, Vec3d) = contact.get_world_points(body_a, body_b);
+            let offset: Vec3d = point_b - point_a;
+            let separation: f64 = offset.dot(normal);
+            let drift: Vec3d = offset - normal * separation;
+            separation < Manifold::BREAK_DISTANCE && drift.get_magnitude() < Manifold::BREAK_DISTANCE
+        });
+    }
+
+    /// Keeps the deepest point, then the points spanning the largest area, so the bodies stay supported
+    fn reduce(&mut self, body_a: &Body, body_b: &Body) {
+        let points: Vec<(Vec3d, f64)> = self.contacts
+            .iter()
+            .map(|contact| {
+                let (point_a, point_b, normal): (Vec3d, Vec3d, Vec3d) = contact.get_world_points(body_a, body_b);
+                (point_a, (point_b - point_a).dot(normal))
+            })
+            .collect();
+
+        let deepest: usize = (0..points.len())
+            .min_by(|&i, &j| points[i].1.total_cmp(&points[j].1))
+            .unwrap_or(0);
+        let mut kept: Vec<usize> = vec![deepest];
+
+        // Each next point adds the most area to the ones kept so far
+        while kept.len() < Manifold::MAX_CONTACTS {
+            let area = |i: usize| -> f64 {
+                let point: Vec3d = points[i].0;
+                match kept.len() {
+                    1 => (point - points[kept[0]].0).get_magnitude_squared(),
+                    _ => (0..kept.len())
+                        .map(|k| {
+                            let edge_start: Vec3d = points[kept[k]].0;
+                            let edge_end: Vec3d = points[kept[(k + 1) % kept.len()]].0;
+                            (edge_start - point).cross(edge_end - point).get_magnitude()
+                        })
+                        .sum()
+                }
+            };
+            let next: Option<usize> = (0..points.len())
+                .filter(|i| !kept.contains(i))
+                .max_by(|&i, &j| area(i).total_cmp(&area(j)));
+            match next {
+                Some(next) => kept.push(next),
+                None => break
+            }
+        }
+
+        self.contacts = std::mem::take(&mut self.contacts)
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| kept.contains(i))
+            .map(|(_, contact)| contact)
+            .collect();
+    }
+}
+
+/// Manifolds of every pair of bodies in contact, kept across steps
+#[derive(Debug, Clone, Default)]
+pub struct ManifoldCache {
+    manifolds: BTreeMap<(BodyHandle, BodyHandle), Manifold> // Ordered by handles, so the solver goes through the contacts in the same order every run
+}
+
+impl ManifoldCache {
+    pub fn new() -> Self {
+        ManifoldCache {
+            manifolds: BTreeMap::new()
+        }
+    }
+
+    /// Adds the points found this step to the manifold of their pair, the lower handle is always body A
+    pub fn add_contacts(&mut self, contacts: &[Contact], bodies: &[Option<Body>]) {
+        for contact in contacts {
+            let contact: Contact = if contact.body_a.0 > contact.body_b.0 { contact.swapped() } else { *contact };
+            let (Some(Some(body_a)), Some(Some(body_b))) = (bodies.get(contact.body_a.0), bodies.get(contact.body_b.0)) else { continue };
+
+            self.manifolds
+                .entry((contact.body_a, contact.body_b))
+                .or_insert_with(|| Manifold::new(contact.body_a, contact.body_b))
+                .add_contact(&contact, body_a, body_b);
+        }
+    }
+
+    /// Drops the points that no longer touch, and the manifolds left empty or of removed bodies
+    pub fn remove_expired(&mut self, bodies: &[Option<Body>]) {
+        self.manifolds.retain(|(handle_a, handle_b), manifold| {
+            let (Some(Some(body_a)), Some(Some(body_b))) = (bodies.get(handle_a.0), bodies.get(handle_b.0)) else { return false };
+            manifold.remove_expired(body_a, body_b);
+            !manifold.contacts.is_empty()
+        });
+    }
+
+    pub fn get_manifold(&self, body_a: BodyHandle, body_b: BodyHandle) -> Option<&Manifold> {
+        self.manifolds.get(&(BodyHandle(body_a.0.min(body_b.0)), BodyHandle(body_a.0.max(body_b.0))))
+    }
+
+    pub fn contacts_mut(&mut self) -> impl Iterator<Item = &mut PenetrationConstraint> {
+        self.manifolds.values_mut().flat_map(|manifold| manifold.contacts.iter_mut())
+    }
+}
@@ -0,0 +1,7 @@
+pub mod constraint;
+pub mod distance;
+pub mod ball_socket;
+pub mod hinge;
+pub mod penetration;
+pub mod manifold;
+pub mod solver;
@@ -0,0 +1,177 @@
+use crate::physics::body::Body;
+use crate::physics::collision::contact::Contact;
+use crate::physics::constraints::constraint::{
+    carry_impulses,
+    Constraint,
+    ConstraintRow
+};
+use crate::physics::scene::BodyHandle;
+use crate::physics::vec::vec3d::Vec3d;
+
+/// Keeps the two bodies of a contact point from moving into each other, it can push them apart but never pull.
+/// Penetration is corrected a bit every step, and bodies hitting fast enough bounce back.
+/// Coulomb friction resists sliding along the contact, up to the friction coefficient times the push,
+/// rolling and spinning friction resist turning the same way when the materials have them.
+#[derive(Debug, Clone)]
+pub struct PenetrationConstraint {
+    pub body_a: BodyHandle,
+    pub body_b: BodyHandle,
+    pub anchor_a: Vec3d, // In A's body space
+    pub anchor_b: Vec3d, // In B's body space
+    pub normal: Vec3d, // In A's body space, from A to B
+    // Found in pre_solve, row 0 pushes along the normal, rows 1 and 2 hold against sliding,
+    // rows 3 and 4 against rolling and row 5 against spinning
+    rows: Vec<ConstraintRow>,
+    friction: f64,
+    rolling_friction: f64,
+    spinning_friction: f64
+}
+
+impl PenetrationConstraint {
+    pub const BAUMGARTE: f64 = 0.2; // Share of the penetration corrected every step
+    pub const SLOP: f64 = 0.01; // m, penetration left alone so resting bodies don't jitter
+    pub const MAX_CORRECTION_SPEED: f64 = 3.0; // m/s, bodies starting deep inside each other come apart without flying off
+    pub const RESTITUTION_THRESHOLD: f64 = 1.0; // m/s, slower hits don't bounce so bodies can come to rest
+    pub const STATIC_FRICTION_SPEED: f64 = 0.1; // m/s, contacts sliding slower than this stick
+
+    pub fn new(contact: &Contact, body_a: &Body) -> Self {
+        PenetrationConstraint {
+            body_a: contact.body_a,
+            body_b: contact.body_b,
+            anchor_a: contact.pt_on_a_local_space,
+            anchor_b: contact.pt_on_b_local_space,
+            normal: body_a.orientation.inverse().rotate_point(contact.normal),
+            rows: Vec::new(),
+            friction: 0.0,
+            rolling_friction: 0.0,
+            spinning_friction: 0.0
+        }
+    }
+
+    /// Starts from the impulses another constraint on the same point applied last step
+    pub fn warm_start_from(&mut self, previous: &PenetrationConstraint) {
+        self.rows = previous.rows.clone();
+    }
+
+    /// The anchors and the normal in world space, where the bodies are now
+    pub fn get_world_points(&self, body_a: &Body, body_b: &Body) -> (Vec3d, Vec3d, Vec3d) {
+        (
+            body_a.body_space_to_world_space(&self.anchor_a),
+            body_b.body_space_to_world_space(&self.anchor_b),
+            body_a.orientation.rotate_point(self.normal)
+        )
+    }
+
+    /// Impulse pushing the bodies apart in the last step
+    pub fn get_impulse(&self) -> f64 {
+        self.rows.first().map_or(0.0, |row| row.get_total_impulse())
+    }
+
+    /// Friction can't hold harder than its coefficient times the push along the normal
+    fn set_friction_bounds(&mut self) {
+        let Some((normal_row, friction_rows)) = self.rows.split_first_mut() else { return };
+        let normal_impulse: f64 = normal_row.get_total_impulse();
+
+        for row in friction_rows {
+            let coefficient: f64 = match row.id {
+                1 | 2 => self.friction,
+                3 | 4 => self.rolling_friction,
+                _ => self.spinning_friction
+            };
+            row.min_impulse = -coefficient * normal_impulse;
+            row.max_impulse = coefficient * normal_impulse;
+        }
+    }
+}
+
+impl Constraint for PenetrationConstraint {
+    fn get_bodies(&self) -> (BodyHandle, BodyHandle) {
+        (self.body_a, self.body_b)
+    }
+
+    fn pre_solve(&mut self, body_a: &Body, body_b: &Body, dt_sec: f64) {
+        let previous: Vec<ConstraintRow> = std::mem::take(&mut self.rows);
+
+        let (point_a, point_b, normal): (Vec3d, Vec3d, Vec3d) = self.get_world_points(body_a, body_b);
+        let r_a: Vec3d = point_a - body_a.get_center_of_mass_world_space();
+        let r_b: Vec3d = point_b - body_b.get_center_of_mass_world_space();
+
+        // C = (p_b - p_a) . n, negative when penetrating
+        let separation: f64 = (point_b - point_a).dot(normal);
+        let mut bias: f64 = if separation > 0.0 {
+            // Not touching yet, the bodies may close the gap this step but no more
+            separation / dt_sec
+        } else {
+            let correction: f64 = PenetrationConstraint::BAUMGARTE / dt_sec * f64::max(-separation - PenetrationConstraint::SLOP, 0.0);
+            -correction.min(PenetrationConstraint::MAX_CORRECTION_SPEED)
+        };
+
+        // Bounce back a share of the speed the bodies hit each other at,
+        // the bounce already pushes the bodies apart, adding the correction on top would add energy
+        let velocity_a: Vec3d = body_a.linear_velocity + body_a.angular_velocity.cross(r_a);
+        let velocity_b: Vec3d = body_b.linear_velocity + body_b.angular_velocity.cross(r_b);
+        let normal_speed: f64 = (velocity_b - velocity_a).dot(normal);
+        if normal_speed < -PenetrationConstraint::RESTITUTION_THRESHOLD {
+            let elasticity: f64 = body_a.material.combine_restitution(&body_b.material);
+            bias = f64::min(bias, elasticity * normal_speed);
+        }
+
+        let mut row: ConstraintRow = ConstraintRow::new(body_a, body_b, -normal, -r_a.cross(normal), normal, r_b.cross(normal), bias);
+        row.min_impulse = 0.0;
+        self.rows.push(row);
+
+        // A contact that barely slides sticks until static friction gives way
+        let relative_velocity: Vec3d = velocity_b - velocity_a;
+        let sliding_speed: f64 = (relative_velocity - normal * normal_speed).get_magnitude();
+        self.friction = if sliding_speed < PenetrationConstraint::STATIC_FRICTION_SPEED {
+            body_a.material.combine_static_friction(&body_b.material)
+        } else {
+            body_a.material.combine_dynamic_friction(&body_b.material)
+        };
+        self.rolling_friction = body_a.material.combine_rolling_friction(&body_b.material);
+        self.spinning_friction = body_a.material.combine_spinning_friction(&body_b.material);
+
+        // Two directions along the contact, the same every step while the normal holds so their impulses carry over
+        let mut tangent_u: Vec3d = Vec3d::zero();
+        let mut tangent_v: Vec3d = Vec3d::zero();
+        normal.get_ortho(&mut tangent_u, &mut tangent_v);
+
+        for (id, tangent) in [(1, tangent_u), (2, tangent_v)] {
+            let mut row: ConstraintRow = ConstraintRow::new(body_a, body_b, -tangent, -r_a.cross(tangent), tangent, r_b.cross(tangent), 0.0);
+            row.id = id;
+            self.rows.push(row);
+        }
+
+        if self.rolling_friction > 0.0 {
+            for (id, tangent) in [(3, tangent_u), (4, tangent_v)] {
+                let mut row: ConstraintRow = ConstraintRow::new_angular(body_a, body_b, &tangent, 0.0, dt_sec);
+                row.id = id;
+                self.rows.push(row);
+            }
+        }
+
+        if self.spinning_friction > 0.0 {
+            let mut row: ConstraintRow = ConstraintRow::new_angular(body_a, body_b, &normal, 0.0, dt_sec);
+            row.id = 5;
+            self.rows.push(row);
+        }
+
+        carry_impulses(&previous, &mut self.rows);
+        self.set_friction_bounds();
+    }
+
+    fn get_rows_mut(&mut self) -> &mut [ConstraintRow] {
+        &mut self.rows
+    }
+
+    /// The push along the normal is solved first, it bounds the friction
+    fn solve(&mut self, body_a: &mut Body, body_b: &mut Body) {
+        let Some(normal_row) = self.rows.first_mut() else { return };
+        normal_row.solve(body_a, body_b);
+        self.set_friction_bounds();
+
+        for row in self.rows.iter_mut().skip(1) {
+            row.solve(body_a, body_b);
+        }
+    }
+}
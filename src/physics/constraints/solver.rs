@@ -0,0 +1,61 @@
+use crate::physics::body::Body;
+use crate::physics::constraints::constraint::Constraint;
+use crate::physics::scene::get_body_pair_mut;
+
+/// Projected Gauss-Seidel solver, applies the impulses of one constraint row at a time,
+/// keeping each row's impulse over the step within its bounds, and goes over every row a few times.
+/// Joints and contacts share the solver so they can push back on each other.
+#[derive(Debug, Clone)]
+pub struct Solver {
+    pub iterations: usize,
+    pub warm_starting: bool // Start from the impulses of last step, resting bodies then settle in a few steps
+}
+
+impl Solver {
+    pub const DEFAULT_ITERATIONS: usize = 10;
+
+    pub fn new() -> Self {
+        Solver {
+            iterations: Solver::DEFAULT_ITERATIONS,
+            warm_starting: true
+        }
+    }
+
+    /// Constraints on a removed body are skipped
+    pub fn solve(&self, bodies: &mut [Option<Body>], constraints: &mut [&mut dyn Constraint], dt_sec: f64) {
+        for constraint in constraints.iter_mut() {
+            let (handle_a, handle_b) = constraint.get_bodies();
+            let (Some(Some(body_a)), Some(Some(body_b))) = (bodies.get(handle_a.0), bodies.get(handle_b.0)) else { continue };
+            constraint.pre_solve(body_a, body_b, dt_sec);
+        }
+
+        for constraint in constraints.iter_mut() {
+            let (handle_a, handle_b) = constraint.get_bodies();
+            let Some((body_a, body_b)) = get_body_pair_mut(bodies, handle_a, handle_b) else { continue };
+            if self.warm_starting {
+                constraint.warm_start(body_a, body_b);
+            } else {
+                constraint.get_rows_mut().iter_mut().for_each(|row| row.clear_impulse());
+            }
+        }
+
+        for _ in 0..self.iterations {
+            for constraint in constraints.iter_mut() {
+                let (handle_a, handle_b) = constraint.get_bodies();
+                if let Some((body_a, body_b)) = get_body_pair_mut(bodies, handle_a, handle_b) {
+                    constraint.solve(body_a, body_b);
+                }
+            }
+        }
+
+        for constraint in constraints.iter_mut() {
+            constraint.post_solve();
+        }
+    }
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Solver::new()
+    }
+}
@@ -0,0 +1,243 @@
+use crate::physics::bounds::Aabb;
+use crate::physics::collision::mesh::{
+    ray_triangle,
+    TriangleCollider
+};
+use crate::physics::triangle_mesh::is_fold_active;
+use crate::physics::vec::vec3d::Vec3d;
+
+/// Grid of heights in a body's space, for terrain.
+/// Samples are cell_scale apart along x (columns) and z (rows), centered on the origin,
+/// their heights along y are scaled by height_scale.
+/// Each cell is split into two triangles, so terrain collides like a triangle mesh without storing one.
+#[derive(Debug, Clone)]
+pub struct Heightfield {
+    pub heights: Vec<f64>, // Row major, rows * columns samples
+    pub rows: usize,
+    pub columns: usize,
+    pub cell_scale: f64,
+    pub height_scale: f64,
+    bounds: Aabb
+}
+
+impl Heightfield {
+    pub fn new(heights: &[f64], rows: usize, columns: usize, cell_scale: f64, height_scale: f64) -> Self {
+        assert!(rows >= 2 && columns >= 2, "a heightfield needs at least one cell");
+        assert_eq!(heights.len(), rows * columns, "a heightfield needs rows * columns heights");
+
+        let mut field: Heightfield = Heightfield {
+            heights: heights.to_vec(),
+            rows,
+            columns,
+            cell_scale,
+            height_scale,
+            bounds: Aabb::empty()
+        };
+
+        for row in 0..rows {
+            for column in 0..columns {
+                field.bounds.expand_point(&field.get_vertex(row, column));
+            }
+        }
+        field
+    }
+
+    /// Sample of the grid, in the body's space
+    pub fn get_vertex(&self, row: usize, column: usize) -> Vec3d {
+        let origin: Vec3d = self.get_origin();
+        Vec3d::new(
+            origin.x + column as f64 * self.cell_scale,
+            self.heights[row * self.columns + column] * self.height_scale,
+            origin.z + row as f64 * self.cell_scale
+        )
+    }
+
+    pub fn get_bounds_local(&self) -> Aabb {
+        self.bounds
+    }
+
+    /// Two triangles per cell
+    pub fn get_triangle_count(&self) -> usize {
+        (self.rows - 1) * (self.columns - 1) * 2
+    }
+
+    /// First hit of a ray with the terrain, in the body's space, stepping through the cells the ray crosses.
+    /// dir is expected to be normalized. Returns the distance along the ray and the surface normal facing the ray.
+    pub fn raycast(&self, start: &Vec3d, dir: &Vec3d, max_dist: f64) -> Option<(f64, Vec3d)> {
+        let t_enter: f64 = self.bounds.intersect_ray(start, dir, max_dist)?;
+
+        let origin: Vec3d = self.get_origin();
+        let cells_x: isize = self.columns as isize - 1;
+        let cells_z: isize = self.rows as isize - 1;
+
+        // Cell where the ray enters the field
+        let entry: Vec3d = *start + *dir * t_enter;
+        let mut column: isize = (((entry.x - origin.x) / self.cell_scale).floor() as isize).clamp(0, cells_x - 1);
+        let mut row: isize = (((entry.z - origin.z) / self.cell_scale).floor() as isize).clamp(0, cells_z - 1);
+
+        // Distance along the ray to the next column and row lines, and between two of them
+        let step_column: isize = if dir.x > 0.0 { 1 } else { -1 };
+        let step_row: isize = if dir.z > 0.0 { 1 } else { -1 };
+        let (mut t_max_x, t_delta_x): (f64, f64) = self.first_crossing(start.x, dir.x, origin.x, column);
+        let (mut t_max_z, t_delta_z): (f64, f64) = self.first_crossing(start.z, dir.z, origin.z, row);
+
+        loop {
+            // The cells are crossed in order, the first cell with a hit has the nearest one
+            let hit: Option<(f64, [Vec3d; 3])> = (0..2)
+                .map(|half| self.get_triangle_points(self.triangle_index(row as usize, column as usize, half)))
+                .filter_map(|points| ray_triangle(start, dir, &points).map(|t| (t, points)))
+                .filter(|(t, _)| *t <= max_dist)
+                .min_by(|a, b| a.0.total_cmp(&b.0));
+
+            if let Some((t, points)) = hit {
+                let mut normal: Vec3d = (points[1] - points[0]).cross(points[2] - points[0]).normalize();
+                if normal.dot(*dir) > 0.0 {
+                    normal = -normal;
+                }
+                return Some((t, normal));
+            }
+
+            if t_max_x < t_max_z {
+                if t_max_x > max_dist {
+                    return None;
+                }
+                column += step_column;
+                t_max_x += t_delta_x;
+            } else {
+                if t_max_z > max_dist {
+                    return None;
+                }
+                row += step_row;
+                t_max_z += t_delta_z;
+            }
+
+            if column < 0 || column >= cells_x || row < 0 || row >= cells_z {
+                return None;
+            }
+        }
+    }
+
+    /// Corner of the grid at row 0 and column 0, at height 0
+    fn get_origin(&self) -> Vec3d {
+        Vec3d::new(
+            -((self.columns - 1) as f64) * self.cell_scale * 0.5,
+            0.0,
+            -((self.rows - 1) as f64) * self.cell_scale * 0.5
+        )
+    }
+
+    /// Distance along a ray to the first grid line past the cell, and between grid lines, along one axis
+    fn first_crossing(&self, start: f64, dir: f64, origin: f64, cell: isize) -> (f64, f64) {
+        if dir == 0.0 {
+            return (f64::MAX, f64::MAX);
+        }
+
+        let next_cell: isize = if dir > 0.0 { cell + 1 } else { cell };
+        let line: f64 = origin + next_cell as f64 * self.cell_scale;
+        ((line - start) / dir, self.cell_scale / f64::abs(dir))
+    }
+
+    fn triangle_index(&self, row: usize, column: usize, half: usize) -> usize {
+        (row * (self.columns - 1) + column) * 2 + half
+    }
+
+    /// Row, column and half of the cell a triangle is in
+    fn triangle_cell(&self, triangle: usize) -> (usize, usize, usize) {
+        let cell: usize = triangle / 2;
+        (cell / (self.columns - 1), cell % (self.columns - 1), triangle % 2)
+    }
+
+    /// Cells overlapped by a range along one axis, None when the range misses the grid
+    fn cell_range(&self, min: f64, max: f64, origin: f64, cells: usize) -> Option<(usize, usize)> {
+        let first: f64 = ((min - origin) / self.cell_scale).floor();
+        let last: f64 = ((max - origin) / self.cell_scale).floor();
+        if last < 0.0 || first >= cells as f64 {
+            return None;
+        }
+        Some((first.max(0.0) as usize, (last as usize).min(cells - 1)))
+    }
+}
+
+impl TriangleCollider for Heightfield {
+    /// Only looks at the cells under the bounds
+    fn query_bounds(&self, bounds: &Aabb) -> Vec<usize> {
+        let mut triangles: Vec<usize> = Vec::new();
+        if !self.bounds.does_intersect(bounds) {
+            return triangles;
+        }
+
+        let origin: Vec3d = self.get_origin();
+        let Some((first_column, last_column)) = self.cell_range(bounds.mins.x, bounds.maxs.x, origin.x, self.columns - 1) else {
+            return triangles;
+        };
+        let Some((first_row, last_row)) = self.cell_range(bounds.mins.z, bounds.maxs.z, origin.z, self.rows - 1) else {
+            return triangles;
+        };
+
+        for row in first_row..=last_row {
+            for column in first_column..=last_column {
+                // Skip cells entirely above or below the bounds
+                let heights: [f64; 4] = [
+                    self.get_vertex(row, column).y,
+                    self.get_vertex(row + 1, column).y,
+                    self.get_vertex(row, column + 1).y,
+                    self.get_vertex(row + 1, column + 1).y
+                ];
+                let lowest: f64 = heights.iter().copied().fold(f64::MAX, f64::min);
+                let highest: f64 = heights.iter().copied().fold(f64::MIN, f64::max);
+                if highest < bounds.mins.y || lowest > bounds.maxs.y {
+                    continue;
+                }
+
+                triangles.push(self.triangle_index(row, column, 0));
+                triangles.push(self.triangle_index(row, column, 1));
+            }
+        }
+        triangles
+    }
+
+    /// The first triangle of a cell is (row, column), (row + 1, column), (row, column + 1),
+    /// the second one is (row, column + 1), (row + 1, column), (row + 1, column + 1), both face up
+    fn get_triangle_points(&self, triangle: usize) -> [Vec3d; 3] {
+        let (row, column, half) = self.triangle_cell(triangle);
+        if half == 0 {
+            [self.get_vertex(row, column), self.get_vertex(row + 1, column), self.get_vertex(row, column + 1)]
+        } else {
+            [self.get_vertex(row, column + 1), self.get_vertex(row + 1, column), self.get_vertex(row + 1, column + 1)]
+        }
+    }
+
+    /// Found from the neighbouring triangle every time, rather than stored for the whole grid
+    fn is_edge_active(&self, triangle: usize, edge: usize) -> bool {
+        let (row, column, half) = self.triangle_cell(triangle);
+
+        // Cell across the edge, the half of it the neighbour is, and the neighbour's corner off the edge
+        let (d_row, d_column, other_half, far_corner): (isize, isize, usize, (isize, isize)) = match (half, edge) {
+            (0, 0) => (0, -1, 1, (1, -1)),
+            (0, 1) => (0, 0, 1, (1, 1)),
+            (0, _) => (-1, 0, 1, (-1, 1)),
+            (_, 0) => (0, 0, 0, (0, 0)),
+            (_, 1) => (1, 0, 0, (2, 0)),
+            (_, _) => (0, 1, 0, (0, 2))
+        };
+
+        // The border of the grid
+        let other_row: isize = row as isize + d_row;
+        let other_column: isize = column as isize + d_column;
+        if other_row < 0 || other_row >= self.rows as isize - 1 || other_column < 0 || other_column >= self.columns as isize - 1 {
+            return true;
+        }
+
+        let points: [Vec3d; 3] = self.get_triangle_points(triangle);
+        let other_points: [Vec3d; 3] =
+            self.get_triangle_points(self.triangle_index(other_row as usize, other_column as usize, other_half));
+        let far_point: Vec3d = self.get_vertex(
+            (row as isize + far_corner.0) as usize,
+            (column as isize + far_corner.1) as usize
+        );
+
+        let normal: Vec3d = (points[1] - points[0]).cross(points[2] - points[0]).normalize();
+        let other_normal: Vec3d = (other_points[1] - other_points[0]).cross(other_points[2] - other_points[0]).normalize();
+        is_fold_active(&normal, &other_normal, &points[edge], &far_point)
+    }
+}
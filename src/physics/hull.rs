@@ -0,0 +1,243 @@
+use crate::physics::mat::mat3::Mat3;
+use crate::physics::vec::vec3d::Vec3d;
+
+// Points closer than this to the hull count as on it
+const HULL_EPSILON: f64 = 1e-6;
+
+/// Face of a hull, wound counter clockwise when seen from outside
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Triangle {
+    pub a: usize,
+    pub b: usize,
+    pub c: usize
+}
+
+impl Triangle {
+    pub fn normal(&self, points: &[Vec3d]) -> Vec3d {
+        let (a, b, c) = (points[self.a], points[self.b], points[self.c]);
+        (b - a).cross(c - a).normalize()
+    }
+
+    /// Positive when the point is in front of the triangle
+    pub fn signed_distance(&self, point: &Vec3d, points: &[Vec3d]) -> f64 {
+        self.normal(points).dot(*point - points[self.a])
+    }
+}
+
+/// Mass properties of a solid hull with uniform density
+#[derive(Debug, Clone, Copy)]
+pub struct MassProperties {
+    pub volume: f64,
+    pub center_mass: Vec3d,
+    pub inertia_tensor: Mat3 // Per unit mass, around the center of mass
+}
+
+/// Convex hull of a point cloud, using incremental quickhull.
+/// Returns the points on the hull and its faces, indexing into those points.
+/// None when there are no points or they are all on the same plane, since such a hull has no volume.
+pub fn build_convex_hull(points: &[Vec3d]) -> Option<(Vec<Vec3d>, Vec<Triangle>)> {
+    let (mut hull_points, mut triangles) = build_tetrahedron(points)?;
+
+    // Every point outside the hull is assigned to one face it is in front of,
+    // points inside the starting tetrahedron can never be on the hull
+    let mut outside_sets: Vec<Vec<Vec3d>> = vec![Vec::new(); triangles.len()];
+    assign_points(&hull_points, &triangles, 0, &mut outside_sets, points);
+
+    while let Some(face) = outside_sets.iter().position(|set| !set.is_empty()) {
+        // Grow the hull towards the point furthest in front of the face
+        let (idx, _) = outside_sets[face]
+            .iter()
+            .enumerate()
+            .map(|(i, point)| (i, triangles[face].signed_distance(point, &hull_points)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .expect("the outside set is not empty");
+        let point: Vec3d = outside_sets[face].swap_remove(idx);
+
+        // The points of the removed faces are either in front of a new face or inside the hull
+        let (first_new_face, orphans) = add_point(&mut hull_points, &mut triangles, &mut outside_sets, point);
+        assign_points(&hull_points, &triangles, first_new_face, &mut outside_sets, &orphans);
+    }
+
+    remove_unreferenced_points(&mut hull_points, &mut triangles);
+    Some((hull_points, triangles))
+}
+
+/// Volume, center of mass and inertia tensor of a closed hull, by summing the tetrahedra
+/// between each face and a point inside the hull
+pub fn hull_mass_properties(points: &[Vec3d], triangles: &[Triangle]) -> MassProperties {
+    let mut reference: Vec3d = Vec3d::zero();
+    for point in points {
+        reference += *point;
+    }
+    reference /= points.len() as f64;
+
+    // Volume and center of mass, the centroid of each tetrahedron weighted by its volume
+    let mut volume: f64 = 0.0;
+    let mut center_mass: Vec3d = Vec3d::zero();
+    for triangle in triangles {
+        let (a, b, c) = (points[triangle.a], points[triangle.b], points[triangle.c]);
+        let tetra_volume: f64 = (a - reference).dot((b - reference).cross(c - reference)) / 6.0;
+        volume += tetra_volume;
+        center_mass += (reference + a + b + c) * (tetra_volume * 0.25);
+    }
+    center_mass /= volume;
+
+    // Second moment of each tetrahedron around the center of mass:
+    // C = V / 20 * (sum of w_i w_i^T + s s^T), with w_i its vertices and s their sum
+    let mut covariance: Mat3 = Mat3::zero();
+    for triangle in triangles {
+        let vertices: [Vec3d; 4] = [
+            reference - center_mass,
+            points[triangle.a] - center_mass,
+            points[triangle.b] - center_mass,
+            points[triangle.c] - center_mass
+        ];
+        let tetra_volume: f64 = (vertices[1] - vertices[0])
+            .dot((vertices[2] - vertices[0]).cross(vertices[3] - vertices[0])) / 6.0;
+        let sum: Vec3d = vertices[0] + vertices[1] + vertices[2] + vertices[3];
+
+        for i in 0..3 {
+            for j in 0..3 {
+                let mut moment: f64 = sum[i] * sum[j];
+                for vertex in &vertices {
+                    moment += vertex[i] * vertex[j];
+                }
+                covariance.rows[i][j] += tetra_volume / 20.0 * moment;
+            }
+        }
+    }
+
+    // I = tr(C) * Id - C, divided by the volume to get it per unit mass
+    let trace: f64 = covariance.trace();
+    let mut inertia_tensor: Mat3 = Mat3::zero();
+    for i in 0..3 {
+        for j in 0..3 {
+            let diagonal: f64 = if i == j { trace } else { 0.0 };
+            inertia_tensor.rows[i][j] = (diagonal - covariance.rows[i][j]) / volume;
+        }
+    }
+
+    MassProperties {
+        volume,
+        center_mass,
+        inertia_tensor
+    }
+}
+
+/// Starts the hull with the largest tetrahedron spanned by extreme points of the cloud.
+/// None when the points don't span a volume.
+fn build_tetrahedron(points: &[Vec3d]) -> Option<(Vec<Vec3d>, Vec<Triangle>)> {
+    let furthest_by = |distance: &dyn Fn(&Vec3d) -> f64| -> Option<Vec3d> {
+        points
+            .iter()
+            .max_by(|a, b| distance(a).total_cmp(&distance(b)))
+            .copied()
+    };
+
+    let p0: Vec3d = furthest_by(&|point| point.x)?;
+
+    // Furthest from p0
+    let p1: Vec3d = furthest_by(&|point| (*point - p0).get_magnitude_squared())?;
+    if (p1 - p0).get_magnitude() <= HULL_EPSILON {
+        return None;
+    }
+
+    // Furthest from the line p0 p1
+    let line: Vec3d = (p1 - p0).normalize();
+    let p2: Vec3d = furthest_by(&|point| (*point - p0).cross(line).get_magnitude_squared())?;
+    let normal: Vec3d = (p1 - p0).cross(p2 - p0);
+    if normal.get_magnitude() <= HULL_EPSILON {
+        return None;
+    }
+
+    // Furthest from the plane p0 p1 p2, on either side
+    let normal: Vec3d = normal.normalize();
+    let p3: Vec3d = furthest_by(&|point| f64::abs((*point - p0).dot(normal)))?;
+    if f64::abs((p3 - p0).dot(normal)) <= HULL_EPSILON {
+        return None;
+    }
+
+    let hull_points: Vec<Vec3d> = vec![p0, p1, p2, p3];
+    let mut triangles: Vec<Triangle> = vec![
+        Triangle { a: 0, b: 1, c: 2 },
+        Triangle { a: 0, b: 2, c: 3 },
+        Triangle { a: 2, b: 1, c: 3 },
+        Triangle { a: 1, b: 0, c: 3 }
+    ];
+
+    // Make the faces point outwards
+    let center: Vec3d = (p0 + p1 + p2 + p3) * 0.25;
+    for triangle in triangles.iter_mut() {
+        if triangle.signed_distance(&center, &hull_points) > 0.0 {
+            std::mem::swap(&mut triangle.b, &mut triangle.c);
+        }
+    }
+
+    Some((hull_points, triangles))
+}
+
+/// Adds each point to the outside set of the first face from first_face on that it is in front of.
+/// Points in front of no face are inside the hull and dropped.
+fn assign_points(hull_points: &[Vec3d], triangles: &[Triangle], first_face: usize,
+                 outside_sets: &mut [Vec<Vec3d>], points: &[Vec3d]) {
+    for point in points {
+        let face: Option<usize> = (first_face..triangles.len())
+            .find(|i| triangles[*i].signed_distance(point, hull_points) > HULL_EPSILON);
+        if let Some(face) = face {
+            outside_sets[face].push(*point);
+        }
+    }
+}
+
+/// Replaces the faces that can see the point with a fan of faces to the point.
+/// The new faces are appended last with empty outside sets.
+/// Returns the index of the first new face and the points that were outside the removed faces.
+fn add_point(hull_points: &mut Vec<Vec3d>, triangles: &mut Vec<Triangle>, outside_sets: &mut Vec<Vec<Vec3d>>,
+             point: Vec3d) -> (usize, Vec<Vec3d>) {
+    let mut removed: Vec<Triangle> = Vec::new();
+    let mut orphans: Vec<Vec3d> = Vec::new();
+    let mut i: usize = 0;
+    while i < triangles.len() {
+        if triangles[i].signed_distance(&point, hull_points) > HULL_EPSILON {
+            removed.push(triangles.swap_remove(i));
+            orphans.append(&mut outside_sets.swap_remove(i));
+        } else {
+            i += 1;
+        }
+    }
+
+    let new_idx: usize = hull_points.len();
+    hull_points.push(point);
+
+    // The border of the hole is made of the edges only one removed face has,
+    // neighbouring faces share an edge in opposite directions
+    let first_new_face: usize = triangles.len();
+    let edges: Vec<(usize, usize)> = removed
+        .iter()
+        .flat_map(|triangle| [(triangle.a, triangle.b), (triangle.b, triangle.c), (triangle.c, triangle.a)])
+        .collect();
+    for (a, b) in edges.iter().filter(|(a, b)| !edges.contains(&(*b, *a))) {
+        triangles.push(Triangle { a: *a, b: *b, c: new_idx });
+    }
+
+    outside_sets.resize(triangles.len(), Vec::new());
+    (first_new_face, orphans)
+}
+
+/// Points can end up inside the hull once it grows past them, keep only the ones a face uses
+fn remove_unreferenced_points(hull_points: &mut Vec<Vec3d>, triangles: &mut [Triangle]) {
+    let mut new_indices: Vec<Option<usize>> = vec![None; hull_points.len()];
+    let mut used_points: Vec<Vec3d> = Vec::new();
+
+    for triangle in triangles.iter_mut() {
+        for idx in [&mut triangle.a, &mut triangle.b, &mut triangle.c] {
+            let new_idx: usize = *new_indices[*idx].get_or_insert_with(|| {
+                used_points.push(hull_points[*idx]);
+                used_points.len() - 1
+            });
+            *idx = new_idx;
+        }
+    }
+
+    *hull_points = used_points;
+}
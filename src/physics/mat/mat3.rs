@@ -44,8 +44,8 @@ impl Mat3 {
 
     pub fn transpose(&self) -> Mat3{
         let mut transpose: Mat3 = Mat3::zero();
-        for i in 0..2 {
-            for j in 0..2 {
+        for i in 0..3 {
+            for j in 0..3 {
                 transpose.rows[i][j] = self.rows[j][i];
             }
         }
@@ -87,7 +87,7 @@ impl Mat3 {
             }
 
             xx = 0;
-            for x in 0..i {
+            for x in 0..3 {
                 if x == i {
                     continue;
                 }
@@ -108,7 +108,7 @@ impl std::ops::Add<Mat3> for Mat3 {
 
     fn add(self, other: Mat3) -> Mat3 {
         let mut rows: [Vec3d; 3] = [Vec3d::zero(); 3];
-        for i in 0..2 {
+        for i in 0..3 {
             rows[i] = self.rows[i] + other.rows[i];
         }
         return Mat3{rows};
@@ -120,7 +120,7 @@ impl std::ops::Mul<f64> for Mat3 {
 
     fn mul(self, scalar: f64) -> Mat3 {
         let mut mat: Mat3 = Mat3::zero();
-        for i in 0..2 {
+        for i in 0..3 {
             mat.rows[i] = self.rows[i] * scalar;
         }
         return mat
@@ -132,10 +132,8 @@ impl std::ops::Mul<Vec3d> for Mat3 {
 
     fn mul(self, vec: Vec3d) -> Vec3d {
         let mut vector : Vec3d = Vec3d::zero();
-        let mut vec_i : Vec3d;
-        for i in 0..2 {
-            vec_i= self.rows[i];
-            vector[i] = vec_i.dot(vec);
+        for i in 0..3 {
+            vector[i] = self.rows[i].dot(vec);
         }
         return vector
     }
@@ -145,9 +143,11 @@ impl std::ops::Mul<Mat3> for Mat3 {
     type Output = Mat3;
 
     fn mul(self, other: Mat3) -> Mat3 {
+        // Each entry is the dot product of a row of self with a column of other
+        let other_t: Mat3 = other.transpose();
         let mut rows: [Vec3d; 3] = [Vec3d::zero(); 3];
-        for i in 0..2 {
-            rows[i] = self.rows[i] * other.rows[i];
+        for (row, self_row) in rows.iter_mut().zip(self.rows) {
+            *row = Vec3d::new(self_row.dot(other_t.rows[0]), self_row.dot(other_t.rows[1]), self_row.dot(other_t.rows[2]));
         }
         return Mat3{rows}
     }
@@ -0,0 +1,125 @@
+#[derive(Debug, Clone, Copy)]
+pub enum ColorT {
+    WHITE,
+    RED,
+    BLUE,
+    GREEN,
+    YELLOW
+}
+
+/// How the coefficients of two bodies in contact give the coefficient of the contact.
+/// When the bodies use different rules, the one further down the list wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CombineRule {
+    Average,
+    GeometricMean,
+    Minimum,
+    Multiply,
+    Maximum
+}
+
+impl CombineRule {
+    pub fn combine(rule_a: CombineRule, a: f64, rule_b: CombineRule, b: f64) -> f64 {
+        match rule_a.max(rule_b) {
+            CombineRule::Average => (a + b) * 0.5,
+            CombineRule::GeometricMean => (a * b).sqrt(),
+            CombineRule::Minimum => a.min(b),
+            CombineRule::Multiply => a * b,
+            CombineRule::Maximum => a.max(b)
+        }
+    }
+}
+
+/// What a body is made of, shared by every body made of the same thing.
+/// Tag a body as rubber or ice rather than tuning its numbers one by one.
+#[derive(Debug, Clone)]
+pub struct PhysicsMaterial {
+    pub name: String,
+    pub density: f64, // kg/m^3, gives the mass of bodies created without one
+    pub restitution: f64, // 0 absorbs the collision, 1 bounces back fully
+    pub static_friction: f64, // Coulomb coefficient while the contact sticks
+    pub dynamic_friction: f64, // Coulomb coefficient once the contact slides
+    pub rolling_friction: f64, // m, lever arm of the torque resisting rolling, 0 rolls freely
+    pub spinning_friction: f64, // m, lever arm of the torque resisting spinning around the contact normal, 0 spins freely
+    pub restitution_combine: CombineRule,
+    pub static_friction_combine: CombineRule,
+    pub dynamic_friction_combine: CombineRule,
+    pub rolling_friction_combine: CombineRule,
+    pub spinning_friction_combine: CombineRule,
+    pub color: ColorT
+}
+
+impl PhysicsMaterial {
+    pub fn new(name: &str, density: f64, restitution: f64, static_friction: f64, dynamic_friction: f64) -> Self {
+        PhysicsMaterial {
+            name: name.to_string(),
+            density,
+            restitution,
+            static_friction,
+            dynamic_friction,
+            rolling_friction: 0.0,
+            spinning_friction: 0.0,
+            restitution_combine: CombineRule::Multiply,
+            static_friction_combine: CombineRule::GeometricMean,
+            dynamic_friction_combine: CombineRule::GeometricMean,
+            rolling_friction_combine: CombineRule::GeometricMean,
+            spinning_friction_combine: CombineRule::GeometricMean,
+            color: ColorT::WHITE
+        }
+    }
+
+    pub fn rubber() -> Self {
+        PhysicsMaterial {
+            restitution_combine: CombineRule::Maximum,
+            rolling_friction: 0.01,
+            color: ColorT::RED,
+            ..PhysicsMaterial::new("rubber", 1100.0, 0.8, 1.0, 0.8)
+        }
+    }
+
+    pub fn ice() -> Self {
+        PhysicsMaterial {
+            static_friction_combine: CombineRule::Minimum,
+            dynamic_friction_combine: CombineRule::Minimum,
+            color: ColorT::BLUE,
+            ..PhysicsMaterial::new("ice", 917.0, 0.1, 0.05, 0.02)
+        }
+    }
+
+    pub fn wood() -> Self {
+        PhysicsMaterial {
+            color: ColorT::YELLOW,
+            ..PhysicsMaterial::new("wood", 700.0, 0.3, 0.5, 0.4)
+        }
+    }
+
+    pub fn steel() -> Self {
+        PhysicsMaterial::new("steel", 7850.0, 0.5, 0.7, 0.5)
+    }
+
+    pub fn combine_restitution(&self, other: &PhysicsMaterial) -> f64 {
+        CombineRule::combine(self.restitution_combine, self.restitution, other.restitution_combine, other.restitution)
+    }
+
+    pub fn combine_static_friction(&self, other: &PhysicsMaterial) -> f64 {
+        CombineRule::combine(self.static_friction_combine, self.static_friction, other.static_friction_combine, other.static_friction)
+    }
+
+    pub fn combine_dynamic_friction(&self, other: &PhysicsMaterial) -> f64 {
+        CombineRule::combine(self.dynamic_friction_combine, self.dynamic_friction, other.dynamic_friction_combine, other.dynamic_friction)
+    }
+
+    pub fn combine_rolling_friction(&self, other: &PhysicsMaterial) -> f64 {
+        CombineRule::combine(self.rolling_friction_combine, self.rolling_friction, other.rolling_friction_combine, other.rolling_friction)
+    }
+
+    pub fn combine_spinning_friction(&self, other: &PhysicsMaterial) -> f64 {
+        CombineRule::combine(self.spinning_friction_combine, self.spinning_friction, other.spinning_friction_combine, other.spinning_friction)
+    }
+}
+
+impl Default for PhysicsMaterial {
+    fn default() -> Self {
+        PhysicsMaterial::new("default", 1000.0, 0.5, 0.6, 0.5)
+    }
+}
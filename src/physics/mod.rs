@@ -2,5 +2,13 @@ pub mod mat;
 pub mod quat;
 pub mod vec;
 pub mod shape;
+pub mod hull;
+pub mod triangle_mesh;
+pub mod heightfield;
+pub mod material;
 pub mod body;
-pub mod scene;
\ No newline at end of file
+pub mod scene;
+pub mod collision;
+pub mod bounds;
+pub mod broadphase;
+pub mod constraints;
\ No newline at end of file
@@ -56,7 +56,7 @@ impl Quat {
                 x: self.x * inv_mag,
                 y: self.y * inv_mag,
                 z: self.z * inv_mag,
-                w: self.x * inv_mag
+                w: self.w * inv_mag
             }
         }
 
@@ -90,17 +90,56 @@ impl Quat {
         self.rotate_point(mat.rows[2])
        ]}
     }
+
+    pub fn get_xyz(self) -> Vec3d {
+        Vec3d {x: self.x, y: self.y, z: self.z}
+    }
+
+    /// Angle of the rotation, between 0 and pi
+    pub fn get_angle(self) -> f64 {
+        2.0 * f64::atan2(self.get_xyz().get_magnitude(), f64::abs(self.w))
+    }
+
+    /// Signed angle of the rotation around axis, between -pi and pi, axis is expected to be normalized
+    pub fn get_twist_angle(self, axis: Vec3d) -> f64 {
+        let angle : f64 = 2.0 * f64::atan2(self.get_xyz().dot(axis), self.w);
+        if angle > std::f64::consts::PI {
+            return angle - 2.0 * std::f64::consts::PI;
+        }
+        if angle < -std::f64::consts::PI {
+            return angle + 2.0 * std::f64::consts::PI;
+        }
+        angle
+    }
+
+    /// Splits the rotation into a twist around axis and a swing off it, self = swing * twist.
+    /// axis is expected to be normalized
+    pub fn swing_twist(self, axis: Vec3d) -> (Quat, Quat) {
+        // The twist keeps the part of the rotation around the axis
+        let projected : Vec3d = axis * self.get_xyz().dot(axis);
+        let mut twist : Quat = Quat::new(projected.x, projected.y, projected.z, self.w);
+
+        // A swing of half a turn has no twist
+        if twist.magnitute_squared() < f64::EPSILON {
+            twist = Quat::zero();
+        }
+        twist = twist.normalize();
+
+        let swing : Quat = self * twist.inverse();
+        (swing, twist)
+    }
 }
 
 impl std::ops::Mul<Quat> for Quat {
     type Output = Quat;
 
     fn mul(self, other: Quat) -> Quat {
+        // Hamilton product
         Quat {
-            x: (self.x * other.w) + (self.w * other.x) - (self.y * other.z) - (self.z * other.y),
-            y: (self.y * other.w) + (self.w * other.y) - (self.z * other.x) - (self.z * other.z),
-            z: (self.z * other.w) + (self.w * other.z) - (self.x * other.y) - (self.z * other.x),
-            w: (self.x * other.w) - (self.x * other.x) - (self.y * other.y) - (self.z * other.z)
+            x: (self.x * other.w) + (self.w * other.x) + (self.y * other.z) - (self.z * other.y),
+            y: (self.y * other.w) + (self.w * other.y) + (self.z * other.x) - (self.x * other.z),
+            z: (self.z * other.w) + (self.w * other.z) + (self.x * other.y) - (self.y * other.x),
+            w: (self.w * other.w) - (self.x * other.x) - (self.y * other.y) - (self.z * other.z)
         }
     }
 }
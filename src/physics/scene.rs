@@ -1,51 +1,355 @@
 use crate::physics::body::*;
-use crate::physics::shape::*;
-use crate::physics::vec::vec3d::Vec3d;
+use crate::physics::bounds::Aabb;
+use crate::physics::broadphase::{
+    swept_bounds,
+    Broadphase,
+    CollisionPair
+};
+use crate::physics::broadphase::sweep_and_prune::SweepAndPrune;
+use crate::physics::collision::contact::{
+    resolve_manifold,
+    Contact
+};
+use crate::physics::collision::intersect::intersect;
+use crate::physics::collision::query::closest_point_on_body;
+use crate::physics::collision::raycast::{
+    get_cast_fraction,
+    ray_body,
+    RaycastHit,
+    ShapeCastHit
+};
+use crate::physics::collision::toi::intersect_dynamic;
+use crate::physics::constraints::constraint::Constraint;
+use crate::physics::constraints::manifold::{
+    Manifold,
+    ManifoldCache
+};
+use crate::physics::constraints::solver::Solver;
 use crate::physics::quat::quat::Quat;
+use crate::physics::shape::{
+    Shape,
+    ShapeConstructor,
+    ShapeT
+};
+use crate::physics::vec::vec3d::Vec3d;
+
+// Stands in for the shape of a shape cast or overlap query in its contacts, it isn't a body of the scene
+const QUERY_HANDLE: BodyHandle = BodyHandle(usize::MAX);
+
+/// Stable reference to a body stored in a `Scene`.
+/// Handles stay valid until the body is removed, removing a body never shifts other handles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BodyHandle(pub usize);
+
+/// Stable reference to a constraint stored in a `Scene`, like `BodyHandle`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConstraintHandle(pub usize);
 
 pub struct Scene {
-    bodies: Vec<Body>
+    bodies: Vec<Option<Body>>,
+    constraints: Vec<Option<Box<dyn Constraint>>>,
+    manifolds: ManifoldCache, // Contacts kept from step to step while the bodies touch
+    pub solver: Solver,
+    broadphase: Box<dyn Broadphase>,
+    pub gravity: Vec3d
 }
 
-// impl Scene {
-//     pub fn new(bodies: Vec<Body>) -> Self {
-//         Scene {bodies: bodies}
-//     }
-
-//     pub fn init(&self) {
-//         // Spawn Object:
-//         let object: Body;
-//         object.shape = ShapeT::new_sphere(1.0); 
-//         object.position = Vec3d::new(0.0, 0.0, 5.0);
-//         object.orientation = Quat::new(0.0, 0.0, 0.0, 1.0);
-//         object.inv_mass = 1.0;
-//         self.bodies.push(  object);
-
-//         // Spawn Ground:
-//         let ground: Body;
-//         ground.shape = ShapeT::new_box(1000.0, 1000.0, 1000.0); // A very large cube
-//         ground.position = Vec3d::new(0.0, 0.0, -1000.0); // The center of the cube, so that object will be spawn on top
-//         ground.orientation = Quat::new(0.0, 0.0, 0.0, 1.0);
-//         ground.inv_mass = 0.0;
-//         self.bodies.push(ground);
-//     }
-
-//     pub fn update(&mut self, dt_sec: f64) {
-//         for &mut body in &mut self.bodies {
-//             //Gravity needs to be an impulse
-//             // I = dp, F = dp/ d t => dp = F * dt => I = F * dt
-//             // F = m * g * sec 
-//             let mass = 1.0 / body.inv_mass;
-//             let gravity_vec = Vec3d::new(0.0, 0.0, -Body::GRAVITY);
-//             let impulse_gravity = gravity_vec * mass * dt_sec;
-//             body.apply_impulse_linear(impulse_gravity);
-//             body.linear_velocity += Vec3d::new(0.0, 0.0, -Body::GRAVITY) * dt_sec;
-//         }
-
-//         for &mut body in &mut self.bodies {
-//             // Position update
-//             // dx = v * dt
-//             body.position += body.linear_velocity * dt_sec; 
-//         }
-//     }
-// }
\ No newline at end of file
+impl Scene {
+    pub fn new() -> Self {
+        Scene::with_broadphase(Box::new(SweepAndPrune::new()))
+    }
+
+    pub fn with_broadphase(broadphase: Box<dyn Broadphase>) -> Self {
+        Scene {
+            bodies: Vec::new(),
+            constraints: Vec::new(),
+            manifolds: ManifoldCache::new(),
+            solver: Solver::new(),
+            broadphase,
+            gravity: Vec3d::new(0.0, -Body::GRAVITY, 0.0)
+        }
+    }
+
+    /// The new broadphase only knows about the bodies after the next update
+    pub fn set_broadphase(&mut self, broadphase: Box<dyn Broadphase>) {
+        self.broadphase = broadphase;
+    }
+
+    /// Bodies whose bounds overlap the given bounds, as of the last update
+    pub fn query_bounds(&self, bounds: &Aabb) -> Vec<BodyHandle> {
+        self.broadphase.query_bounds(bounds)
+    }
+
+    /// Bodies whose bounds are hit by the ray, as of the last update
+    pub fn query_ray(&self, origin: &Vec3d, dir: &Vec3d, max_dist: f64) -> Vec<BodyHandle> {
+        self.broadphase.query_ray(origin, &dir.normalize(), max_dist)
+    }
+
+    /// First body hit by the ray within max_dist, among the bodies filter accepts.
+    /// The broadphase finds the bodies the ray can reach, as of the last update.
+    pub fn raycast(&self, origin: &Vec3d, dir: &Vec3d, max_dist: f64,
+                   filter: impl Fn(BodyHandle, &Body) -> bool) -> Option<RaycastHit> {
+        let dir: Vec3d = dir.normalize();
+
+        self.broadphase
+            .query_ray(origin, &dir, max_dist)
+            .into_iter()
+            .filter_map(|handle| {
+                let body: &Body = self.get_body(handle).filter(|body| filter(handle, body))?;
+                let (distance, normal) = ray_body(body, origin, &dir, max_dist)?;
+                Some(RaycastHit {
+                    body: handle,
+                    point: *origin + dir * distance,
+                    normal,
+                    distance,
+                    fraction: get_cast_fraction(distance, max_dist)
+                })
+            })
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+
+    /// First body touched by the shape as it is swept from position along dir for up to max_dist,
+    /// among the bodies filter accepts. The shape keeps its orientation and the bodies stand still.
+    pub fn shape_cast(&self, shape: &ShapeT, position: &Vec3d, orientation: &Quat, dir: &Vec3d, max_dist: f64,
+                      filter: impl Fn(BodyHandle, &Body) -> bool) -> Option<ShapeCastHit> {
+        // Moving at unit speed for max_dist seconds, the time of impact is the distance swept
+        let mut cast_body: Body = Body::new_shape(shape.clone());
+        cast_body.position = *position;
+        cast_body.orientation = *orientation;
+        cast_body.linear_velocity = dir.normalize();
+
+        // The sweep never has to go past the furthest body, long or unbounded casts would lose precision
+        let mut reach: Aabb = shape.get_bounds(position, orientation);
+        for (_, body) in self.bodies() {
+            reach.expand_bounds(&body.shape.get_bounds(&body.position, &body.orientation));
+        }
+        let reach_dist: f64 = reach.corners().iter().map(|corner| (*corner - *position).get_magnitude()).fold(0.0, f64::max);
+        let sweep_dist: f64 = max_dist.min(reach_dist);
+
+        self.broadphase
+            .query_bounds(&swept_bounds(&cast_body, sweep_dist))
+            .into_iter()
+            .filter_map(|handle| {
+                let body: &Body = self.get_body(handle).filter(|body| filter(handle, body))?;
+
+                let mut still_body: Body = body.clone();
+                still_body.linear_velocity = Vec3d::zero();
+                still_body.angular_velocity = Vec3d::zero();
+
+                let contact: Contact = intersect_dynamic(QUERY_HANDLE, &cast_body, handle, &still_body, sweep_dist)
+                    .into_iter()
+                    .min_by(|a, b| a.time_of_impact.total_cmp(&b.time_of_impact))?;
+                Some(ShapeCastHit {
+                    body: handle,
+                    point: contact.pt_on_b_world_space,
+                    normal: -contact.normal,
+                    distance: contact.time_of_impact,
+                    fraction: get_cast_fraction(contact.time_of_impact, max_dist)
+                })
+            })
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+
+    /// Bodies touching the sphere
+    pub fn overlap_sphere(&self, center: &Vec3d, radius: f64) -> Vec<BodyHandle> {
+        self.overlap_shape(&ShapeT::new_sphere(radius), center, &Quat::zero())
+    }
+
+    /// Bodies touching the box, sized like `ShapeT::new_box`
+    pub fn overlap_box(&self, center: &Vec3d, width: f64, length: f64, height: f64, orientation: &Quat) -> Vec<BodyHandle> {
+        self.overlap_shape(&ShapeT::new_box(width, length, height), center, orientation)
+    }
+
+    /// Bodies touching the shape placed at position, checked with the narrowphase.
+    /// The broadphase finds the bodies near the shape, as of the last update.
+    pub fn overlap_shape(&self, shape: &ShapeT, position: &Vec3d, orientation: &Quat) -> Vec<BodyHandle> {
+        let mut probe: Body = Body::new_shape(shape.clone());
+        probe.position = *position;
+        probe.orientation = *orientation;
+
+        self.broadphase
+            .query_bounds(&probe.shape.get_bounds(position, orientation))
+            .into_iter()
+            .filter(|handle| {
+                self.get_body(*handle).is_some_and(|body| !intersect(QUERY_HANDLE, &probe, *handle, body).is_empty())
+            })
+            .collect()
+    }
+
+    /// Point of the body closest to the given point, the point itself when it is inside the body.
+    /// Returns None when the body was removed.
+    pub fn closest_point_on_body(&self, handle: BodyHandle, point: &Vec3d) -> Option<Vec3d> {
+        self.get_body(handle).map(|body| closest_point_on_body(body, point))
+    }
+
+    pub fn add_body(&mut self, mut body: Body) -> BodyHandle {
+        if body.shape.is_static_only() && body.body_type == BodyType::Dynamic {
+            body.body_type = BodyType::Static;
+        }
+
+        self.bodies.push(Some(body));
+        BodyHandle(self.bodies.len() - 1)
+    }
+
+    pub fn remove_body(&mut self, handle: BodyHandle) -> Option<Body> {
+        self.bodies.get_mut(handle.0)?.take()
+    }
+
+    pub fn get_body(&self, handle: BodyHandle) -> Option<&Body> {
+        self.bodies.get(handle.0)?.as_ref()
+    }
+
+    pub fn get_body_mut(&mut self, handle: BodyHandle) -> Option<&mut Body> {
+        self.bodies.get_mut(handle.0)?.as_mut()
+    }
+
+    /// Constraints on a removed body are skipped until they are removed too
+    pub fn add_constraint(&mut self, constraint: Box<dyn Constraint>) -> ConstraintHandle {
+        self.constraints.push(Some(constraint));
+        ConstraintHandle(self.constraints.len() - 1)
+    }
+
+    pub fn remove_constraint(&mut self, handle: ConstraintHandle) -> Option<Box<dyn Constraint>> {
+        self.constraints.get_mut(handle.0)?.take()
+    }
+
+    pub fn get_constraint(&self, handle: ConstraintHandle) -> Option<&dyn Constraint> {
+        self.constraints.get(handle.0)?.as_deref()
+    }
+
+    /// Points the two bodies touched at over the last steps, None when they don't touch
+    pub fn get_manifold(&self, handle_a: BodyHandle, handle_b: BodyHandle) -> Option<&Manifold> {
+        self.manifolds.get_manifold(handle_a, handle_b)
+    }
+
+    pub fn bodies(&self) -> impl Iterator<Item = (BodyHandle, &Body)> {
+        self.bodies
+            .iter()
+            .enumerate()
+            .filter_map(|(i, body)| body.as_ref().map(|body| (BodyHandle(i), body)))
+    }
+
+    pub fn update(&mut self, dt_sec: f64) {
+        for body in self.bodies.iter_mut().flatten() {
+            // Kinematic bodies follow their target, not gravity
+            body.move_towards_target(dt_sec);
+
+            // Bodies with infinite mass are not affected by gravity
+            if body.get_inv_mass() == 0.0 {
+                continue;
+            }
+
+            // Gravity needs to be an impulse
+            // I = dp, F = dp/dt => dp = F * dt => I = F * dt
+            // F = m * g
+            let mass = 1.0 / body.get_inv_mass();
+            let impulse_gravity = self.gravity * mass * dt_sec;
+            body.apply_impulse_linear(impulse_gravity);
+        }
+
+        // Broadphase
+        let collision_pairs: Vec<CollisionPair> = self.broadphase.find_pairs(&self.bodies, dt_sec);
+
+        // Narrowphase (perform actual collision detection)
+        let mut manifolds: Vec<Vec<Contact>> = Vec::new();
+        for pair in &collision_pairs {
+            let (Some(body_a), Some(body_b)) = (self.get_body(pair.a), self.get_body(pair.b)) else { continue };
+
+            // Skip body pairs with infinite mass, kinematic bodies go through static ones and each other
+            if body_a.get_inv_mass() == 0.0 && body_b.get_inv_mass() == 0.0 {
+                continue;
+            }
+
+            let manifold: Vec<Contact> = if body_a.continuous || body_b.continuous {
+                intersect_dynamic(pair.a, body_a, pair.b, body_b, dt_sec)
+            } else {
+                intersect(pair.a, body_a, pair.b, body_b)
+            };
+
+            if !manifold.is_empty() {
+                manifolds.push(manifold);
+            }
+        }
+
+        // Bodies already touching are solved together with the joints,
+        // the impacts later in the step are resolved when they happen
+        let (touching, mut manifolds): (Vec<Vec<Contact>>, Vec<Vec<Contact>>) = manifolds
+            .into_iter()
+            .partition(|manifold| !manifold[0].from_ccd);
+        self.manifolds.remove_expired(&self.bodies);
+        for manifold in &touching {
+            self.manifolds.add_contacts(manifold, &self.bodies);
+        }
+        self.solve_constraints(dt_sec);
+
+        // Resolve the contacts in the order they happen during the step
+        manifolds.sort_by(|a, b| a[0].time_of_impact.total_cmp(&b[0].time_of_impact));
+
+        // Advance every body to the time of each impact, then resolve it
+        let mut accumulated_time: f64 = 0.0;
+        for manifold in &manifolds {
+            let dt_local: f64 = manifold[0].time_of_impact - accumulated_time;
+            self.integrate(dt_local);
+
+            let (handle_a, handle_b) = (manifold[0].body_a, manifold[0].body_b);
+            if let Some((body_a, body_b)) = get_body_pair_mut(&mut self.bodies, handle_a, handle_b) {
+                resolve_manifold(manifold, body_a, body_b);
+            }
+            accumulated_time += dt_local;
+        }
+
+        // Update the positions for the rest of this step
+        let time_remaining: f64 = dt_sec - accumulated_time;
+        if time_remaining > 0.0 {
+            self.integrate(time_remaining);
+        }
+    }
+
+    /// Solves the joints and the touching contacts together
+    fn solve_constraints(&mut self, dt_sec: f64) {
+        let mut constraints: Vec<&mut dyn Constraint> = Vec::new();
+        for joint in self.constraints.iter_mut().flatten() {
+            constraints.push(joint.as_mut());
+        }
+        for contact in self.manifolds.contacts_mut() {
+            constraints.push(contact);
+        }
+
+        self.solver.solve(&mut self.bodies, &mut constraints, dt_sec);
+    }
+
+    fn integrate(&mut self, dt_sec: f64) {
+        for body in self.bodies.iter_mut().flatten() {
+            // Static bodies never move, kinematic ones move by their velocity alone
+            if body.body_type == BodyType::Static {
+                continue;
+            }
+
+            body.update(dt_sec);
+        }
+    }
+}
+
+/// Both bodies of a pair at once, None when they are the same body or either one was removed
+pub fn get_body_pair_mut(bodies: &mut [Option<Body>], handle_a: BodyHandle, handle_b: BodyHandle) -> Option<(&mut Body, &mut Body)> {
+    if handle_a.0 == handle_b.0 || handle_a.0.max(handle_b.0) >= bodies.len() {
+        return None;
+    }
+
+    // Split the storage so both bodies can be borrowed mutably at once
+    let (low, high) = (handle_a.0.min(handle_b.0), handle_a.0.max(handle_b.0));
+    let (head, tail) = bodies.split_at_mut(high);
+    let (body_low, body_high) = (head.get_mut(low)?.as_mut()?, tail.get_mut(0)?.as_mut()?);
+
+    if handle_a.0 < handle_b.0 {
+        Some((body_low, body_high))
+    } else {
+        Some((body_high, body_low))
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Scene::new()
+    }
+}
@@ -1,8 +1,44 @@
 use crate::physics::vec::vec3d::Vec3d;
+use crate::physics::mat::mat3::Mat3;
+use crate::physics::quat::quat::Quat;
+use crate::physics::bounds::Aabb;
+use crate::physics::hull::{
+    build_convex_hull,
+    hull_mass_properties,
+    MassProperties,
+    Triangle
+};
+use crate::physics::triangle_mesh::TriangleMesh;
+use crate::physics::heightfield::Heightfield;
+use std::sync::Arc;
+
+// Half size of the bounds of a plane, it has to fit in the broadphase while covering any level
+pub const PLANE_EXTENT: f64 = 1.0e6;
+
+/************* STRUCTS ****************/
+
+/// Shape placed in the space of a compound shape
+#[derive(Debug, Clone)]
+pub struct ChildShape {
+    pub shape: ShapeT,
+    pub position: Vec3d,
+    pub orientation: Quat
+}
+
+impl ChildShape {
+    pub fn new(shape: ShapeT, position: Vec3d, orientation: Quat) -> Self {
+        ChildShape { shape, position, orientation }
+    }
+
+    /// Center of mass of the child in the compound's space
+    pub fn get_center_mass(&self) -> Vec3d {
+        self.position + self.orientation.rotate_point(self.shape.get_center_mass())
+    }
+}
 
 /************* ENUMS ****************/
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum ShapeT {
     SphereShape {
         radius : f64,
@@ -13,19 +49,89 @@ pub enum ShapeT {
         length: f64,
         height: f64,
         center_mass: Vec3d
+    },
+    ConvexShape {
+        points: Vec<Vec3d>, // Points of the hull, in the shape's space
+        triangles: Vec<Triangle>,
+        center_mass: Vec3d,
+        inertia_tensor: Mat3
+    },
+    // Capsules and cylinders stand along the y axis, half_height doesn't include the capsule's caps
+    CapsuleShape {
+        radius: f64,
+        half_height: f64,
+        center_mass: Vec3d
+    },
+    CylinderShape {
+        radius: f64,
+        half_height: f64,
+        center_mass: Vec3d
+    },
+    // Children share the body's density, so their mass follows their volume
+    CompoundShape {
+        children: Vec<ChildShape>,
+        center_mass: Vec3d,
+        inertia_tensor: Mat3
+    },
+    // Level geometry, a surface with no volume that only static bodies can have.
+    // Shared, so copies of the body don't copy the triangles.
+    TriangleMeshShape {
+        mesh: Arc<TriangleMesh>
+    },
+    // Terrain, static only like triangle meshes
+    HeightfieldShape {
+        field: Arc<Heightfield>
+    },
+    // Half-space behind the plane normal . x = offset, for floors and walls.
+    // Always static, and bodies that end up behind it are still pushed out.
+    PlaneShape {
+        normal: Vec3d,
+        offset: f64
     }
 }
 
 /************* IMPLEMENTS ****************/
 
-pub trait ShapeConstructor {
+pub trait ShapeConstructor: Sized {
     fn new_sphere(radius: f64) -> Self;
     fn new_box(width: f64, length: f64, height: f64) -> Self;
+    fn new_convex(points: &[Vec3d]) -> Option<Self>;
+    fn new_capsule(radius: f64, half_height: f64) -> Self;
+    fn new_cylinder(radius: f64, half_height: f64) -> Self;
+    fn new_compound(children: Vec<ChildShape>) -> Option<Self>;
+    fn new_triangle_mesh(vertices: &[Vec3d], indices: &[usize]) -> Self;
+    fn new_heightfield(heights: &[f64], rows: usize, columns: usize, cell_scale: f64, height_scale: f64) -> Self;
+    fn new_plane(normal: Vec3d, offset: f64) -> Self;
 }
 
 pub trait Shape {
     fn get_volume(&self) -> f64;
     fn get_center_mass(&self) -> Vec3d;
+    fn get_inertia_tensor(&self) -> Mat3;
+    fn fastest_linear_speed(&self, angular_velocity: &Vec3d, dir: &Vec3d) -> f64;
+    fn get_bounds(&self, position: &Vec3d, orientation: &Quat) -> Aabb;
+    fn get_bounds_local(&self) -> Aabb;
+    fn support(&self, dir: &Vec3d) -> Vec3d;
+}
+
+/// Corners of a box centered on the origin
+pub fn box_corners(width: f64, length: f64, height: f64) -> [Vec3d; 8] {
+    let mut corners: [Vec3d; 8] = [Vec3d::zero(); 8];
+    for (i, corner) in corners.iter_mut().enumerate() {
+        *corner = Vec3d::new(
+            if i & 1 == 0 { -width } else { width } * 0.5,
+            if i & 2 == 0 { -length } else { length } * 0.5,
+            if i & 4 == 0 { -height } else { height } * 0.5
+        );
+    }
+    corners
+}
+
+/// R * I * R^T, expresses a tensor of a rotated shape in the space it was rotated in
+fn rotate_tensor(orientation: &Quat, tensor: Mat3) -> Mat3 {
+    // rotate_matrix rotates the rows, rotating the rows of the transpose again gives R * I * R^T
+    let half_rotated: Mat3 = orientation.rotate_matrix(tensor);
+    orientation.rotate_matrix(half_rotated.transpose())
 }
 
 
@@ -35,9 +141,86 @@ impl ShapeConstructor for ShapeT {
     }
 
     fn new_box(width: f64, length: f64, height: f64) -> Self {
-        let center_mass = Vec3d::new(width/2.0, length/2.0, height/2.0);
+        // The box is centered on its body's position
+        let center_mass = Vec3d::zero();
         ShapeT::BoxShape { width, length, height, center_mass }
     }
+
+    /// None when the points don't span a volume
+    fn new_convex(points: &[Vec3d]) -> Option<Self> {
+        // Only the points on the hull matter, the mass properties come from the solid it encloses
+        let (points, triangles) = build_convex_hull(points)?;
+        let mass_properties: MassProperties = hull_mass_properties(&points, &triangles);
+        Some(ShapeT::ConvexShape {
+            points,
+            triangles,
+            center_mass: mass_properties.center_mass,
+            inertia_tensor: mass_properties.inertia_tensor
+        })
+    }
+
+    fn new_capsule(radius: f64, half_height: f64) -> Self {
+        ShapeT::CapsuleShape { radius, half_height, center_mass: Vec3d::zero() }
+    }
+
+    fn new_cylinder(radius: f64, half_height: f64) -> Self {
+        ShapeT::CylinderShape { radius, half_height, center_mass: Vec3d::zero() }
+    }
+
+    /// None without children, with children that have no volume, or with static-only children
+    fn new_compound(children: Vec<ChildShape>) -> Option<Self> {
+        if children.iter().any(|child| child.shape.is_static_only()) {
+            return None;
+        }
+        let volume: f64 = children.iter().map(|child| child.shape.get_volume()).sum();
+        if volume <= 0.0 {
+            return None;
+        }
+
+        let mut center_mass: Vec3d = Vec3d::zero();
+        for child in &children {
+            center_mass += child.get_center_mass() * (child.shape.get_volume() / volume);
+        }
+
+        // Move each child's tensor to the compound's space and around its center of mass,
+        // with the parallel axis theorem: I = I_child + m * ((d . d) * Id - d d^T)
+        let mut inertia_tensor: Mat3 = Mat3::zero();
+        for child in &children {
+            let mass: f64 = child.shape.get_volume() / volume;
+            let d: Vec3d = child.get_center_mass() - center_mass;
+
+            let mut parallel_axis: Mat3 = Mat3::identity() * d.dot(d);
+            for i in 0..3 {
+                for j in 0..3 {
+                    parallel_axis.rows[i][j] -= d[i] * d[j];
+                }
+            }
+
+            let child_tensor: Mat3 = rotate_tensor(&child.orientation, child.shape.get_inertia_tensor());
+            inertia_tensor = inertia_tensor + (child_tensor + parallel_axis) * mass;
+        }
+
+        Some(ShapeT::CompoundShape { children, center_mass, inertia_tensor })
+    }
+
+    fn new_triangle_mesh(vertices: &[Vec3d], indices: &[usize]) -> Self {
+        ShapeT::TriangleMeshShape { mesh: Arc::new(TriangleMesh::new(vertices, indices)) }
+    }
+
+    fn new_heightfield(heights: &[f64], rows: usize, columns: usize, cell_scale: f64, height_scale: f64) -> Self {
+        ShapeT::HeightfieldShape { field: Arc::new(Heightfield::new(heights, rows, columns, cell_scale, height_scale)) }
+    }
+
+    fn new_plane(normal: Vec3d, offset: f64) -> Self {
+        ShapeT::PlaneShape { normal: normal.normalize(), offset }
+    }
+}
+
+impl ShapeT {
+    /// Shapes without a finite volume can't have a mass, bodies made of them never move
+    pub fn is_static_only(&self) -> bool {
+        matches!(self, ShapeT::TriangleMeshShape { .. } | ShapeT::HeightfieldShape { .. } | ShapeT::PlaneShape { .. })
+    }
 }
 
 impl Shape for ShapeT {
@@ -48,6 +231,21 @@ impl Shape for ShapeT {
 
             ShapeT::SphereShape { radius, .. } =>
                 (4.0/3.0) * std::f64::consts::PI * f64::powf(*radius, 3.0),
+
+            ShapeT::ConvexShape { points, triangles, .. } =>
+                hull_mass_properties(points, triangles).volume,
+
+            // A cylinder with a sphere split over its ends
+            ShapeT::CapsuleShape { radius, half_height, .. } =>
+                std::f64::consts::PI * radius * radius * (2.0 * half_height + (4.0/3.0) * radius),
+
+            ShapeT::CylinderShape { radius, half_height, .. } =>
+                std::f64::consts::PI * radius * radius * 2.0 * half_height,
+
+            ShapeT::CompoundShape { children, .. } =>
+                children.iter().map(|child| child.shape.get_volume()).sum(),
+
+            ShapeT::TriangleMeshShape { .. } | ShapeT::HeightfieldShape { .. } | ShapeT::PlaneShape { .. } => 0.0,
         }
     }
 
@@ -55,6 +253,309 @@ impl Shape for ShapeT {
         match self {
             ShapeT::SphereShape { center_mass, .. } => *center_mass,
             ShapeT::BoxShape { center_mass, .. } => *center_mass,
+            ShapeT::ConvexShape { center_mass, .. } => *center_mass,
+            ShapeT::CapsuleShape { center_mass, .. } => *center_mass,
+            ShapeT::CylinderShape { center_mass, .. } => *center_mass,
+            ShapeT::CompoundShape { center_mass, .. } => *center_mass,
+            ShapeT::TriangleMeshShape { .. } | ShapeT::HeightfieldShape { .. } | ShapeT::PlaneShape { .. } => Vec3d::zero(),
+        }
+    }
+
+    fn get_inertia_tensor(&self) -> Mat3 {
+        // Inertia tensors are per unit mass, the body scales them by its mass
+        match self {
+            ShapeT::SphereShape { radius, .. } => {
+                // I = 2/5 * r^2 on every axis
+                Mat3::identity() * (2.0 * radius * radius / 5.0)
+            },
+
+            ShapeT::BoxShape { width, length, height, .. } => {
+                // Solid cuboid: I_xx = (y^2 + z^2) / 12 and so on
+                let (dx, dy, dz) = (width * width, length * length, height * height);
+                let mut tensor = Mat3::zero();
+                tensor.rows[0][0] = (dy + dz) / 12.0;
+                tensor.rows[1][1] = (dx + dz) / 12.0;
+                tensor.rows[2][2] = (dx + dy) / 12.0;
+                tensor
+            },
+
+            // Integrated over the hull's tetrahedra when the shape was built
+            ShapeT::ConvexShape { inertia_tensor, .. } => *inertia_tensor,
+
+            ShapeT::CapsuleShape { radius, half_height, .. } => {
+                // Share the mass between the cylinder and the caps by volume
+                let (r2, h) = (radius * radius, *half_height);
+                let cylinder_volume: f64 = 2.0 * h;
+                let caps_volume: f64 = (4.0/3.0) * radius;
+                let cylinder_mass: f64 = cylinder_volume / (cylinder_volume + caps_volume);
+                let caps_mass: f64 = caps_volume / (cylinder_volume + caps_volume);
+
+                // Each cap's center of mass is 3/8 r past the end of the cylinder,
+                // the parallel axis theorem moves the caps' inertia there
+                let mut tensor = Mat3::zero();
+                tensor.rows[1][1] = cylinder_mass * r2 / 2.0 + caps_mass * 2.0 * r2 / 5.0;
+                let ortho: f64 = cylinder_mass * (h * h / 3.0 + r2 / 4.0)
+                    + caps_mass * (2.0 * r2 / 5.0 + h * h + 3.0 * h * radius / 4.0);
+                tensor.rows[0][0] = ortho;
+                tensor.rows[2][2] = ortho;
+                tensor
+            },
+
+            ShapeT::CylinderShape { radius, half_height, .. } => {
+                // Solid cylinder: I_yy = r^2 / 2, I_xx = I_zz = (3 r^2 + height^2) / 12
+                let (r2, height) = (radius * radius, 2.0 * half_height);
+                let mut tensor = Mat3::zero();
+                tensor.rows[0][0] = (3.0 * r2 + height * height) / 12.0;
+                tensor.rows[1][1] = r2 / 2.0;
+                tensor.rows[2][2] = (3.0 * r2 + height * height) / 12.0;
+                tensor
+            },
+
+            ShapeT::CompoundShape { inertia_tensor, .. } => *inertia_tensor,
+
+            // Static bodies don't rotate
+            ShapeT::TriangleMeshShape { .. } | ShapeT::HeightfieldShape { .. } | ShapeT::PlaneShape { .. } => Mat3::zero(),
+        }
+    }
+
+    fn fastest_linear_speed(&self, angular_velocity: &Vec3d, dir: &Vec3d) -> f64 {
+        // Fastest speed along dir of any point on the surface caused by the rotation,
+        // angular_velocity and dir are given in the shape's space
+        match self {
+            // Spinning a sphere around its center doesn't move its surface
+            ShapeT::SphereShape { .. } => 0.0,
+
+            ShapeT::BoxShape { width, length, height, center_mass } => {
+                let mut max_speed: f64 = 0.0;
+                for point in box_corners(*width, *length, *height) {
+                    let r: Vec3d = point - *center_mass;
+                    let linear_velocity: Vec3d = angular_velocity.cross(r);
+                    max_speed = max_speed.max(dir.dot(linear_velocity));
+                }
+                max_speed
+            },
+
+            ShapeT::ConvexShape { points, center_mass, .. } => {
+                let mut max_speed: f64 = 0.0;
+                for point in points {
+                    let r: Vec3d = *point - *center_mass;
+                    let linear_velocity: Vec3d = angular_velocity.cross(r);
+                    max_speed = max_speed.max(dir.dot(linear_velocity));
+                }
+                max_speed
+            },
+
+            // Like a sphere, the rounded part doesn't move its surface, only the ends of the segment matter
+            ShapeT::CapsuleShape { half_height, center_mass, .. } => {
+                let mut max_speed: f64 = 0.0;
+                for end in [Vec3d::new(0.0, *half_height, 0.0), Vec3d::new(0.0, -*half_height, 0.0)] {
+                    let r: Vec3d = end - *center_mass;
+                    let linear_velocity: Vec3d = angular_velocity.cross(r);
+                    max_speed = max_speed.max(dir.dot(linear_velocity));
+                }
+                max_speed
+            },
+
+            ShapeT::CylinderShape { center_mass, .. } => {
+                // dir . (w x r) = r . (dir x w), which is largest at the support point along dir x w
+                let k: Vec3d = dir.cross(*angular_velocity);
+                if k.get_magnitude_squared() == 0.0 {
+                    return 0.0;
+                }
+                (self.support(&k) - *center_mass).dot(k).max(0.0)
+            },
+
+            ShapeT::CompoundShape { children, center_mass, .. } => {
+                // A child's points move with its own spin plus the rotation of its center around the compound's
+                let mut max_speed: f64 = 0.0;
+                for child in children {
+                    let inverse_orient: Quat = child.orientation.inverse();
+                    let r: Vec3d = child.get_center_mass() - *center_mass;
+                    let child_speed: f64 = child.shape.fastest_linear_speed(
+                        &inverse_orient.rotate_point(*angular_velocity),
+                        &inverse_orient.rotate_point(*dir)
+                    );
+                    max_speed = max_speed.max(dir.dot(angular_velocity.cross(r)) + child_speed);
+                }
+                max_speed
+            },
+
+            ShapeT::TriangleMeshShape { .. } | ShapeT::HeightfieldShape { .. } | ShapeT::PlaneShape { .. } => 0.0,
+        }
+    }
+
+    fn get_bounds(&self, position: &Vec3d, orientation: &Quat) -> Aabb {
+        match self {
+            ShapeT::SphereShape { radius, .. } => {
+                let extent: Vec3d = Vec3d::new(*radius, *radius, *radius);
+                Aabb::new(*position - extent, *position + extent)
+            },
+
+            ShapeT::BoxShape { width, length, height, .. } => {
+                // Bound the rotated corners
+                let mut bounds: Aabb = Aabb::empty();
+                for corner in box_corners(*width, *length, *height) {
+                    bounds.expand_point(&(orientation.rotate_point(corner) + *position));
+                }
+                bounds
+            },
+
+            ShapeT::ConvexShape { points, .. } => {
+                let mut bounds: Aabb = Aabb::empty();
+                for point in points {
+                    bounds.expand_point(&(orientation.rotate_point(*point) + *position));
+                }
+                bounds
+            },
+
+            ShapeT::CapsuleShape { radius, half_height, .. } => {
+                // Bound the spheres at both ends of the segment
+                let axis: Vec3d = orientation.rotate_point(Vec3d::new(0.0, *half_height, 0.0));
+                let extent: Vec3d = Vec3d::new(*radius, *radius, *radius);
+                let mut bounds: Aabb = Aabb::empty();
+                for end in [*position + axis, *position - axis] {
+                    bounds.expand_point(&(end - extent));
+                    bounds.expand_point(&(end + extent));
+                }
+                bounds
+            },
+
+            ShapeT::CylinderShape { radius, half_height, .. } => {
+                // Along each world axis the caps reach r * sin of their tilt, past the ends of the axis
+                let axis: Vec3d = orientation.rotate_point(Vec3d::new(0.0, 1.0, 0.0));
+                let mut extent: Vec3d = Vec3d::zero();
+                for i in 0..3 {
+                    extent[i] = f64::abs(axis[i]) * half_height + radius * (1.0 - axis[i] * axis[i]).max(0.0).sqrt();
+                }
+                Aabb::new(*position - extent, *position + extent)
+            },
+
+            ShapeT::CompoundShape { children, .. } => {
+                let mut bounds: Aabb = Aabb::empty();
+                for child in children {
+                    let child_position: Vec3d = *position + orientation.rotate_point(child.position);
+                    let child_orientation: Quat = *orientation * child.orientation;
+                    bounds.expand_bounds(&child.shape.get_bounds(&child_position, &child_orientation));
+                }
+                bounds
+            },
+
+            ShapeT::TriangleMeshShape { mesh } => {
+                // Bound the rotated corners of the mesh's own bounds, rather than every vertex
+                let mut bounds: Aabb = Aabb::empty();
+                for corner in mesh.get_bounds_local().corners() {
+                    bounds.expand_point(&(orientation.rotate_point(corner) + *position));
+                }
+                bounds
+            },
+
+            ShapeT::HeightfieldShape { field } => {
+                let mut bounds: Aabb = Aabb::empty();
+                for corner in field.get_bounds_local().corners() {
+                    bounds.expand_point(&(orientation.rotate_point(corner) + *position));
+                }
+                bounds
+            },
+
+            ShapeT::PlaneShape { normal, offset } => {
+                let extent: Vec3d = Vec3d::new(PLANE_EXTENT, PLANE_EXTENT, PLANE_EXTENT);
+                let mut bounds: Aabb = Aabb::new(-extent, extent);
+
+                // Axis aligned planes only fill the bounds up to the plane, tilted ones fill all of them
+                let world_normal: Vec3d = orientation.rotate_point(*normal);
+                let plane_point: Vec3d = *position + orientation.rotate_point(*normal * *offset);
+                for i in 0..3 {
+                    if world_normal[i] > 1.0 - f64::EPSILON {
+                        bounds.maxs[i] = plane_point[i];
+                    } else if world_normal[i] < -1.0 + f64::EPSILON {
+                        bounds.mins[i] = plane_point[i];
+                    }
+                }
+                bounds
+            }
+        }
+    }
+
+    fn get_bounds_local(&self) -> Aabb {
+        self.get_bounds(&Vec3d::zero(), &Quat::zero())
+    }
+
+    fn support(&self, dir: &Vec3d) -> Vec3d {
+        // Furthest point of the shape along dir, in the shape's space
+        match self {
+            ShapeT::SphereShape { radius, center_mass } =>
+                *center_mass + dir.normalize() * *radius,
+
+            ShapeT::BoxShape { width, length, height, .. } => {
+                let half_extents: Vec3d = Vec3d::new(*width, *length, *height) * 0.5;
+                let mut point: Vec3d = half_extents;
+                for i in 0..3 {
+                    if dir[i] < 0.0 {
+                        point[i] = -half_extents[i];
+                    }
+                }
+                point
+            },
+
+            ShapeT::ConvexShape { points, .. } => {
+                *points
+                    .iter()
+                    .max_by(|a, b| a.dot(*dir).total_cmp(&b.dot(*dir)))
+                    .expect("a convex shape has points")
+            },
+
+            ShapeT::CapsuleShape { radius, half_height, center_mass } => {
+                let end: f64 = if dir.y < 0.0 { -half_height } else { *half_height };
+                *center_mass + Vec3d::new(0.0, end, 0.0) + dir.normalize() * *radius
+            },
+
+            ShapeT::CylinderShape { radius, half_height, center_mass } => {
+                let end: f64 = if dir.y < 0.0 { -half_height } else { *half_height };
+                let radial: Vec3d = Vec3d::new(dir.x, 0.0, dir.z);
+                let rim: Vec3d = if radial.get_magnitude_squared() > 0.0 {
+                    radial.normalize() * *radius
+                } else {
+                    Vec3d::zero()
+                };
+                *center_mass + Vec3d::new(0.0, end, 0.0) + rim
+            },
+
+            // The furthest of the children's furthest points
+            ShapeT::CompoundShape { children, .. } => {
+                children
+                    .iter()
+                    .map(|child| {
+                        let dir_child_space: Vec3d = child.orientation.inverse().rotate_point(*dir);
+                        child.position + child.orientation.rotate_point(child.shape.support(&dir_child_space))
+                    })
+                    .max_by(|a, b| a.dot(*dir).total_cmp(&b.dot(*dir)))
+                    .expect("a compound shape has children")
+            },
+
+            // The mesh isn't convex, this is the support point of its hull
+            ShapeT::TriangleMeshShape { mesh } => {
+                *mesh.vertices
+                    .iter()
+                    .max_by(|a, b| a.dot(*dir).total_cmp(&b.dot(*dir)))
+                    .expect("a triangle mesh has vertices")
+            },
+
+            ShapeT::HeightfieldShape { field } => {
+                (0..field.rows)
+                    .flat_map(|row| (0..field.columns).map(move |column| field.get_vertex(row, column)))
+                    .max_by(|a, b| a.dot(*dir).total_cmp(&b.dot(*dir)))
+                    .expect("a heightfield has samples")
+            },
+
+            // A half-space has no furthest point, this is the furthest one within its bounds
+            ShapeT::PlaneShape { normal, offset } => {
+                let tangent: Vec3d = *dir - *normal * dir.dot(*normal);
+                let mut point: Vec3d = *normal * *offset + tangent.normalize() * PLANE_EXTENT;
+                if dir.dot(*normal) < 0.0 {
+                    point -= *normal * PLANE_EXTENT;
+                }
+                point
+            }
         }
     }
 }
\ No newline at end of file
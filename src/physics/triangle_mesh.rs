@@ -0,0 +1,249 @@
+use crate::physics::bounds::Aabb;
+use crate::physics::collision::mesh::{
+    ray_triangle,
+    TriangleCollider
+};
+use crate::physics::hull::Triangle;
+use crate::physics::vec::vec3d::Vec3d;
+
+const MAX_LEAF_TRIANGLES: usize = 4;
+// Neighbouring faces closer than this to coplanar (cosine of the angle between their normals) form a flat seam
+const FLAT_EDGE_COSINE: f64 = 0.9999;
+// Neighbours whose far point is less than this behind a face fold it concave
+const CONVEX_EDGE_EPSILON: f64 = 1e-6;
+
+/// Node of the bounding volume hierarchy, leaves hold a range of `TriangleMesh::bvh_triangles`
+#[derive(Debug, Clone)]
+struct BvhNode {
+    bounds: Aabb,
+    left: usize,
+    right: usize,
+    first: usize,
+    count: usize // Zero for internal nodes
+}
+
+impl BvhNode {
+    fn is_leaf(&self) -> bool {
+        self.count > 0
+    }
+}
+
+/// Triangle soup in a body's space, for static level geometry.
+/// Triangles are wound counter clockwise when seen from their front.
+#[derive(Debug, Clone)]
+pub struct TriangleMesh {
+    pub vertices: Vec<Vec3d>,
+    pub triangles: Vec<Triangle>,
+
+    // Whether a body can hit each edge (ab, bc, ca) of each triangle,
+    // seams between coplanar faces and concave folds can't push bodies sideways
+    active_edges: Vec<[bool; 3]>,
+
+    nodes: Vec<BvhNode>,
+    bvh_triangles: Vec<usize>
+}
+
+impl TriangleMesh {
+    /// Builds the mesh from a vertex buffer and an index buffer with three indices per triangle
+    pub fn new(vertices: &[Vec3d], indices: &[usize]) -> Self {
+        assert!(indices.len().is_multiple_of(3), "a triangle mesh needs three indices per triangle, got {} indices", indices.len());
+        assert!(indices.iter().all(|idx| *idx < vertices.len()), "a triangle mesh index is out of its {} vertices", vertices.len());
+
+        let triangles: Vec<Triangle> = indices
+            .chunks_exact(3)
+            .map(|idx| Triangle { a: idx[0], b: idx[1], c: idx[2] })
+            .collect();
+
+        let mut mesh: TriangleMesh = TriangleMesh {
+            vertices: vertices.to_vec(),
+            active_edges: Vec::new(),
+            nodes: Vec::new(),
+            bvh_triangles: (0..triangles.len()).collect(),
+            triangles
+        };
+
+        mesh.active_edges = mesh.find_active_edges();
+        if !mesh.triangles.is_empty() {
+            mesh.build_node(0, mesh.triangles.len());
+        }
+        mesh
+    }
+
+    pub fn get_bounds_local(&self) -> Aabb {
+        match self.nodes.first() {
+            Some(root) => root.bounds,
+            None => Aabb::empty()
+        }
+    }
+
+    /// Nearest hit of a ray with the mesh, in the mesh's space, walking down the BVH.
+    /// dir is expected to be normalized. Returns the distance along the ray and the normal of the hit face, facing the ray.
+    pub fn raycast(&self, start: &Vec3d, dir: &Vec3d, max_dist: f64) -> Option<(f64, Vec3d)> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut nearest: Option<(f64, usize)> = None;
+        let mut stack: Vec<usize> = vec![0];
+        while let Some(node_idx) = stack.pop() {
+            // Skip nodes the ray only reaches past the nearest hit so far
+            let reach: f64 = nearest.map_or(max_dist, |(distance, _)| distance);
+            let node: &BvhNode = &self.nodes[node_idx];
+            if node.bounds.intersect_ray(start, dir, reach).is_none() {
+                continue;
+            }
+
+            if !node.is_leaf() {
+                stack.push(node.left);
+                stack.push(node.right);
+                continue;
+            }
+
+            for &triangle in &self.bvh_triangles[node.first..node.first + node.count] {
+                if let Some(distance) = ray_triangle(start, dir, &self.get_triangle_points(triangle)) {
+                    if distance <= nearest.map_or(max_dist, |(nearest_distance, _)| nearest_distance) {
+                        nearest = Some((distance, triangle));
+                    }
+                }
+            }
+        }
+
+        let (distance, triangle) = nearest?;
+        let mut normal: Vec3d = self.triangles[triangle].normal(&self.vertices);
+        if normal.dot(*dir) > 0.0 {
+            normal = -normal;
+        }
+        Some((distance, normal))
+    }
+
+    fn triangle_bounds(&self, triangle: usize) -> Aabb {
+        let mut bounds: Aabb = Aabb::empty();
+        bounds.expand_points(&self.get_triangle_points(triangle));
+        bounds
+    }
+
+    fn triangle_centroid(&self, triangle: usize) -> Vec3d {
+        let [a, b, c] = self.get_triangle_points(triangle);
+        (a + b + c) / 3.0
+    }
+
+    /// Builds the node for bvh_triangles[first..first + count] and its children, returns its index
+    fn build_node(&mut self, first: usize, count: usize) -> usize {
+        let mut bounds: Aabb = Aabb::empty();
+        let mut centroid_bounds: Aabb = Aabb::empty();
+        for &triangle in &self.bvh_triangles[first..first + count] {
+            bounds.expand_bounds(&self.triangle_bounds(triangle));
+            centroid_bounds.expand_point(&self.triangle_centroid(triangle));
+        }
+
+        let node_idx: usize = self.nodes.len();
+        self.nodes.push(BvhNode { bounds, left: 0, right: 0, first, count });
+        if count <= MAX_LEAF_TRIANGLES {
+            return node_idx;
+        }
+
+        // Split at the median along the axis where the centroids spread the most
+        let widths: [f64; 3] = [centroid_bounds.width_x(), centroid_bounds.width_y(), centroid_bounds.width_z()];
+        let axis: usize = (0..3).fold(0, |best, i| if widths[i] > widths[best] { i } else { best });
+
+        let half: usize = count / 2;
+        let mut order: Vec<usize> = self.bvh_triangles[first..first + count].to_vec();
+        order.select_nth_unstable_by(half, |a, b| {
+            self.triangle_centroid(*a)[axis].total_cmp(&self.triangle_centroid(*b)[axis])
+        });
+        self.bvh_triangles[first..first + count].copy_from_slice(&order);
+
+        let left: usize = self.build_node(first, half);
+        let right: usize = self.build_node(first + half, count - half);
+        let node: &mut BvhNode = &mut self.nodes[node_idx];
+        node.left = left;
+        node.right = right;
+        node.count = 0;
+        node_idx
+    }
+
+    /// An edge is active when it is on the border of the mesh or folds the surface convex
+    fn find_active_edges(&self) -> Vec<[bool; 3]> {
+        let edge_vertices = |triangle: &Triangle| -> [(usize, usize); 3] {
+            [(triangle.a, triangle.b), (triangle.b, triangle.c), (triangle.c, triangle.a)]
+        };
+
+        // Faces sharing each edge, keyed by its vertices in increasing order
+        let mut edge_faces: std::collections::HashMap<(usize, usize), Vec<usize>> = std::collections::HashMap::new();
+        for (i, triangle) in self.triangles.iter().enumerate() {
+            for (a, b) in edge_vertices(triangle) {
+                edge_faces.entry((a.min(b), a.max(b))).or_default().push(i);
+            }
+        }
+
+        let mut active_edges: Vec<[bool; 3]> = vec![[true; 3]; self.triangles.len()];
+        for (i, (triangle, active)) in self.triangles.iter().zip(active_edges.iter_mut()).enumerate() {
+            let normal: Vec3d = triangle.normal(&self.vertices);
+
+            for ((a, b), edge_active) in edge_vertices(triangle).into_iter().zip(active.iter_mut()) {
+                let faces: &Vec<usize> = &edge_faces[&(a.min(b), a.max(b))];
+                let neighbours: Vec<usize> = faces.iter().filter(|face| **face != i).copied().collect();
+
+                // Border edges, and edges shared by more than two faces, stay active
+                if neighbours.len() != 1 {
+                    continue;
+                }
+                let other: usize = neighbours[0];
+
+                let other_triangle: &Triangle = &self.triangles[other];
+                let other_normal: Vec3d = other_triangle.normal(&self.vertices);
+                let far_point: usize = [other_triangle.a, other_triangle.b, other_triangle.c]
+                    .into_iter()
+                    .find(|vertex| *vertex != a && *vertex != b)
+                    .unwrap_or(other_triangle.a);
+
+                *edge_active = is_fold_active(&normal, &other_normal, &self.vertices[a], &self.vertices[far_point]);
+            }
+        }
+
+        active_edges
+    }
+}
+
+impl TriangleCollider for TriangleMesh {
+    /// Walks down the BVH, bounds are in the mesh's space
+    fn query_bounds(&self, bounds: &Aabb) -> Vec<usize> {
+        let mut triangles: Vec<usize> = Vec::new();
+        if self.nodes.is_empty() {
+            return triangles;
+        }
+
+        let mut stack: Vec<usize> = vec![0];
+        while let Some(node_idx) = stack.pop() {
+            let node: &BvhNode = &self.nodes[node_idx];
+            if !node.bounds.does_intersect(bounds) {
+                continue;
+            }
+
+            if node.is_leaf() {
+                triangles.extend_from_slice(&self.bvh_triangles[node.first..node.first + node.count]);
+            } else {
+                stack.push(node.left);
+                stack.push(node.right);
+            }
+        }
+        triangles
+    }
+
+    fn get_triangle_points(&self, triangle: usize) -> [Vec3d; 3] {
+        let Triangle { a, b, c } = self.triangles[triangle];
+        [self.vertices[a], self.vertices[b], self.vertices[c]]
+    }
+
+    fn is_edge_active(&self, triangle: usize, edge: usize) -> bool {
+        self.active_edges[triangle][edge]
+    }
+}
+
+/// Whether the edge between a face and its neighbour can push bodies, it can't when the faces
+/// are coplanar or fold the surface concave. far_point is the neighbour's corner off the edge.
+pub fn is_fold_active(normal: &Vec3d, other_normal: &Vec3d, edge_point: &Vec3d, far_point: &Vec3d) -> bool {
+    let is_flat: bool = f64::abs(normal.dot(*other_normal)) > FLAT_EDGE_COSINE;
+    let is_convex: bool = normal.dot(*far_point - *edge_point) < -CONVEX_EDGE_EPSILON;
+    !is_flat && is_convex
+}
@@ -21,6 +21,12 @@ impl Vec3d {
         ).sqrt()
     }
 
+    pub fn get_magnitude_squared(&self) -> f64 {
+        self.x.powi(2) +
+        self.y.powi(2) +
+        self.z.powi(2)
+    }
+
     pub fn normalize(&self) -> Vec3d {
         // Normal is N = V / |V|
         // Each coordinate is divided by magnitute
@@ -53,7 +59,7 @@ impl Vec3d {
         }
     }
 
-    pub fn dot(&mut self, other: Vec3d) -> f64 {
+    pub fn dot(&self, other: Vec3d) -> f64 {
         // Vector A: A = (A₁, A₂, A₃, ..., Aₙ)
         // Vector B: B = (B₁, B₂, B₃, ..., Bₙ)
         // A ⋅ B = A₁ * B₁ + A₂ * B₂ + A₃ * B₃ + ... + Aₙ * Bₙ
@@ -73,7 +79,7 @@ impl Vec3d {
         *u = w.cross(n);
         *u = u.normalize();
 
-        *v = w.cross(n);
+        *v = n.cross(*u);
         *v = v.normalize();
     }
 
@@ -131,6 +137,18 @@ impl std::ops::MulAssign<Vec3d> for Vec3d {
     }
 }
 
+impl std::ops::Neg for Vec3d {
+    type Output = Vec3d;
+
+    fn neg(self) -> Vec3d {
+        Vec3d {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z
+        }
+    }
+}
+
 impl std::ops::Sub<Vec3d> for Vec3d {
     type Output = Vec3d;
 
@@ -1,16 +1,27 @@
 
 use bevy::prelude::*;
 use bevy::prelude::shape as bevy_shape;
+use bevy::render::mesh::Indices;
+use bevy::render::render_resource::PrimitiveTopology;
 
 // mod physics::bodies::;
 use crate::physics::{
     body,
-    shape
+    heightfield,
+    hull,
+    material,
+    scene::BodyHandle,
+    shape,
+    vec::vec3d
 };
+use crate::visuals::plugin::PhysicsBody;
 
 use body::Body;
-use body::ColorT;
+use material::ColorT;
+use heightfield::Heightfield;
+use hull::Triangle;
 use shape::ShapeT;
+use vec3d::Vec3d as myVec3;
 
 
 #[derive(Debug, Clone)]
@@ -18,7 +29,8 @@ pub struct BodyAdapter {
     pub my_body: Body,
     pub mesh: Mesh,
     pub material: StandardMaterial,
-    pub transform: Transform
+    pub transform: Transform,
+    pub children: Vec<BodyAdapter> // Parts of a compound shape, their transforms are relative to the body
 }
 
 impl BodyAdapter {
@@ -28,20 +40,71 @@ impl BodyAdapter {
         let mesh: Mesh = match my_body_shape {   
             ShapeT::SphereShape { radius, .. } => {
                 Mesh::from(bevy_shape::UVSphere {
-                    radius: radius as f32,
+                    radius: *radius as f32,
                     sectors: 32,
                     stacks: 64 // Number of subdivision - (this value is arbitrary, and pretty much looks like a sphere)
                 })
             },
             ShapeT::BoxShape { width, length, height, .. } => {
                 Mesh::from(bevy_shape::Box::new (
-                width as f32,
-                length as f32,
-                height as f32,
+                *width as f32,
+                *length as f32,
+                *height as f32,
                 ))
+            },
+            ShapeT::ConvexShape { points, triangles, .. } => {
+                BodyAdapter::flat_shaded_mesh(points, triangles)
+            },
+            ShapeT::CapsuleShape { radius, half_height, .. } => {
+                Mesh::from(bevy_shape::Capsule {
+                    radius: *radius as f32,
+                    depth: (2.0 * half_height) as f32, // Length of the cylinder between the caps
+                    ..default()
+                })
+            },
+            ShapeT::CylinderShape { radius, half_height, .. } => {
+                Mesh::from(bevy_shape::Cylinder {
+                    radius: *radius as f32,
+                    height: (2.0 * half_height) as f32,
+                    resolution: 32,
+                    segments: 1
+                })
+            },
+            // The children carry the meshes
+            ShapeT::CompoundShape { .. } => {
+                Mesh::new(PrimitiveTopology::TriangleList)
+            },
+            ShapeT::TriangleMeshShape { mesh } => {
+                BodyAdapter::flat_shaded_mesh(&mesh.vertices, &mesh.triangles)
+            },
+            ShapeT::HeightfieldShape { field } => {
+                BodyAdapter::heightfield_mesh(field)
+            },
+            ShapeT::PlaneShape { normal, offset } => {
+                BodyAdapter::plane_mesh(normal, *offset)
             }
         };
 
+        let children: Vec<BodyAdapter> = match my_body_shape {
+            ShapeT::CompoundShape { children, .. } => {
+                children.iter().map(|child| {
+                    let mut child_adapter = BodyAdapter::new(my_body.child_body(child));
+                    child_adapter.transform = Transform::from_xyz(
+                        child.position.x as f32,
+                        child.position.y as f32,
+                        child.position.z as f32
+                    ).with_rotation(Quat::from_xyzw(
+                        child.orientation.x as f32,
+                        child.orientation.y as f32,
+                        child.orientation.z as f32,
+                        child.orientation.w as f32
+                    ));
+                    child_adapter
+                }).collect()
+            },
+            _ => Vec::new()
+        };
+
         let material: StandardMaterial = 
             match my_body.get_color() {
                 ColorT::WHITE => Color::WHITE,
@@ -55,18 +118,172 @@ impl BodyAdapter {
 
         // let material : StandardMaterial = Color::WHITE.into();
 
-        let transform = Transform::from_xyz(
-            my_body.position.x as f32,
-            my_body.position.y as f32,
-            my_body.position.z as f32
-        );
+        let transform = BodyAdapter::body_transform(&my_body);
 
         return BodyAdapter {
             my_body,
             mesh,
             material,
-            transform
+            transform,
+            children
+        }
+    }
+
+    /// Spawns the body's entity, linked to its body in the physics scene.
+    /// Compound shapes get an entity without a mesh, with one child entity per part.
+    pub fn spawn(self, commands: &mut Commands, meshes: &mut Assets<Mesh>,
+                 materials: &mut Assets<StandardMaterial>, handle: BodyHandle) -> Entity {
+        if self.children.is_empty() {
+            return commands.spawn((PbrBundle {
+                mesh: meshes.add(self.mesh),
+                material: materials.add(self.material),
+                transform: self.transform,
+                ..default()
+            }, PhysicsBody(handle))).id();
+        }
+
+        commands.spawn((SpatialBundle::from_transform(self.transform), PhysicsBody(handle)))
+            .with_children(|parent| {
+                for child in self.children {
+                    child.spawn_part(parent, meshes, materials);
+                }
+            })
+            .id()
+    }
+
+    /// Part of a compound shape, it follows its parent entity
+    fn spawn_part(self, parent: &mut ChildBuilder, meshes: &mut Assets<Mesh>, materials: &mut Assets<StandardMaterial>) {
+        if self.children.is_empty() {
+            parent.spawn(PbrBundle {
+                mesh: meshes.add(self.mesh),
+                material: materials.add(self.material),
+                transform: self.transform,
+                ..default()
+            });
+            return;
+        }
+
+        // A compound inside a compound
+        parent.spawn(SpatialBundle::from_transform(self.transform))
+            .with_children(|parent| {
+                for child in self.children {
+                    child.spawn_part(parent, meshes, materials);
+                }
+            });
+    }
+
+    pub fn body_transform(my_body: &Body) -> Transform {
+        Transform::from_xyz(
+            my_body.position.x as f32,
+            my_body.position.y as f32,
+            my_body.position.z as f32
+        ).with_rotation(Quat::from_xyzw(
+            my_body.orientation.x as f32,
+            my_body.orientation.y as f32,
+            my_body.orientation.z as f32,
+            my_body.orientation.w as f32
+        ))
+    }
+
+    /// Flat shaded mesh of a hull or a triangle mesh, every face gets its own vertices so the normals don't blend
+    fn flat_shaded_mesh(points: &[myVec3], triangles: &[Triangle]) -> Mesh {
+        let mut positions: Vec<[f32; 3]> = Vec::new();
+        let mut normals: Vec<[f32; 3]> = Vec::new();
+
+        for triangle in triangles {
+            let normal: myVec3 = triangle.normal(points);
+            for idx in [triangle.a, triangle.b, triangle.c] {
+                let point: myVec3 = points[idx];
+                positions.push([point.x as f32, point.y as f32, point.z as f32]);
+                normals.push([normal.x as f32, normal.y as f32, normal.z as f32]);
+            }
+        }
+
+        let indices: Vec<u32> = (0..positions.len() as u32).collect();
+
+        let mut mesh: Mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        mesh.set_indices(Some(Indices::U32(indices)));
+        mesh
+    }
+
+    /// Large quad on the plane, the plane itself never ends
+    fn plane_mesh(normal: &myVec3, offset: f64) -> Mesh {
+        const HALF_SIZE: f64 = 100.0;
+
+        let mut u: myVec3 = myVec3::zero();
+        let mut v: myVec3 = myVec3::zero();
+        normal.get_ortho(&mut u, &mut v);
+
+        let center: myVec3 = *normal * offset;
+        let corners: [myVec3; 4] = [
+            center - u * HALF_SIZE - v * HALF_SIZE,
+            center + u * HALF_SIZE - v * HALF_SIZE,
+            center + u * HALF_SIZE + v * HALF_SIZE,
+            center - u * HALF_SIZE + v * HALF_SIZE
+        ];
+        let positions: Vec<[f32; 3]> = corners.iter().map(|p| [p.x as f32, p.y as f32, p.z as f32]).collect();
+        let normals: Vec<[f32; 3]> = vec![[normal.x as f32, normal.y as f32, normal.z as f32]; 4];
+
+        // Wound counter clockwise when seen from the side the normal points to
+        let (a, b, c) = (corners[0], corners[1], corners[2]);
+        let indices: Vec<u32> = if (b - a).cross(c - a).dot(*normal) > 0.0 {
+            vec![0, 1, 2, 0, 2, 3]
+        } else {
+            vec![0, 2, 1, 0, 3, 2]
+        };
+
+        let mut mesh: Mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        mesh.set_indices(Some(Indices::U32(indices)));
+        mesh
+    }
+
+    /// Smooth shaded terrain, one vertex per sample with the normals of the faces around it averaged
+    fn heightfield_mesh(field: &Heightfield) -> Mesh {
+        let mut positions: Vec<[f32; 3]> = Vec::new();
+        for row in 0..field.rows {
+            for column in 0..field.columns {
+                let vertex: myVec3 = field.get_vertex(row, column);
+                positions.push([vertex.x as f32, vertex.y as f32, vertex.z as f32]);
+            }
         }
+
+        // Same split of the cells as the collision triangles
+        let mut indices: Vec<u32> = Vec::new();
+        let mut normals: Vec<myVec3> = vec![myVec3::zero(); positions.len()];
+        for row in 0..field.rows - 1 {
+            for column in 0..field.columns - 1 {
+                let corner: usize = row * field.columns + column;
+                let next_row: usize = corner + field.columns;
+                for triangle in [[corner, next_row, corner + 1], [corner + 1, next_row, next_row + 1]] {
+                    let [a, b, c] = triangle.map(|idx| field.get_vertex(idx / field.columns, idx % field.columns));
+
+                    // Not normalized, larger faces weigh more
+                    let normal: myVec3 = (b - a).cross(c - a);
+                    for idx in triangle {
+                        normals[idx] += normal;
+                        indices.push(idx as u32);
+                    }
+                }
+            }
+        }
+
+        let normals: Vec<[f32; 3]> = normals
+            .iter()
+            .map(|normal| {
+                let normal: myVec3 = normal.normalize();
+                [normal.x as f32, normal.y as f32, normal.z as f32]
+            })
+            .collect();
+
+        let mut mesh: Mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        mesh.set_indices(Some(Indices::U32(indices)));
+        mesh
     }
 
     pub fn get_mesh(self) -> Mesh {
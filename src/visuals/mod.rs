@@ -1 +1,2 @@
-pub mod adapters;
\ No newline at end of file
+pub mod adapters;
+pub mod plugin;
\ No newline at end of file
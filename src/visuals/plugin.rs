@@ -0,0 +1,40 @@
+use bevy::prelude::*;
+
+use crate::physics::scene::{
+    BodyHandle,
+    Scene
+};
+
+use crate::visuals::adapters::BodyAdapter;
+
+/// The physics world, stepped on Bevy's fixed timestep
+#[derive(Resource, Default)]
+pub struct PhysicsScene(pub Scene);
+
+/// Links an entity to the body whose position and orientation it renders
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PhysicsBody(pub BodyHandle);
+
+pub struct PhysicsPlugin;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PhysicsScene>()
+            .add_systems(FixedUpdate, step_scene)
+            .add_systems(Update, sync_transforms);
+    }
+}
+
+fn step_scene(time: Res<Time>, mut scene: ResMut<PhysicsScene>) {
+    // Inside FixedUpdate, `Time` is the fixed clock
+    scene.0.update(time.delta_seconds_f64());
+}
+
+fn sync_transforms(scene: Res<PhysicsScene>, mut query: Query<(&PhysicsBody, &mut Transform)>) {
+    for (physics_body, mut transform) in &mut query {
+        // The body may have been removed from the scene, leave its entity where it is
+        if let Some(body) = scene.0.get_body(physics_body.0) {
+            *transform = BodyAdapter::body_transform(body);
+        }
+    }
+}